@@ -126,52 +126,6 @@ fn find_cycle_dfs(
     false
 }
 
-fn reveal_start_tile(cycle: &Vec<Coord>) -> Tile {
-    let start = cycle[0];
-    let first = cycle[1];
-    let last = cycle[cycle.len() - 1];
-
-    let first_delta = Coord {
-        row: first.row - start.row,
-        col: first.col - start.col,
-    };
-
-    let last_delta = Coord {
-        row: last.row - start.row,
-        col: last.col - start.col,
-    };
-
-    let north = Coord { row: -1, col: 0 };
-    let south = Coord { row: 1, col: 0 };
-    let east = Coord { row: 0, col: 1 };
-    let west = Coord { row: 0, col: -1 };
-
-    let check = |dir1: Coord, dir2: Coord| {
-        first_delta == dir1 && last_delta == dir2 || first_delta == dir2 && last_delta == dir1
-    };
-
-    if check(north, south) {
-        return Tile::NorthSouthPipe;
-    }
-    if check(east, west) {
-        return Tile::EastWestPipe;
-    }
-    if check(north, east) {
-        return Tile::NorthEastPipe;
-    }
-    if check(north, west) {
-        return Tile::NorthWestPipe;
-    }
-    if check(south, east) {
-        return Tile::SouthEastPipe;
-    }
-    if check(south, west) {
-        return Tile::SouthWestPipe;
-    }
-
-    unreachable!()
-}
-
 fn part_one(map: &Vec<Vec<Tile>>, start: Coord) {
     let cycle = find_cycle(map, start);
     let steps = cycle.len() / 2;
@@ -180,63 +134,15 @@ fn part_one(map: &Vec<Vec<Tile>>, start: Coord) {
 }
 
 fn part_two(map: &Vec<Vec<Tile>>, start: Coord) {
-    let mut map = map.clone();
-    let n = map.len();
-    let m = map[0].len();
-    let cycle = find_cycle(&map, start);
-
-    // Fix start tile
-    map[start.row as usize][start.col as usize] = reveal_start_tile(&cycle);
-
-    // Cache edges in each row
-    let mut edges_in_row = vec![vec![]; n];
-
-    for node in cycle.iter() {
-        let row = node.row as usize;
-        let col = node.col as usize;
-        edges_in_row[row].push(col);
-    }
-
-    for row in 0..n {
-        edges_in_row[row].sort();
-    }
-
-    // "Raycasting"
-    let mut count = 0;
-
-    for row in 0..n {
-        for col in 0..m {
-            let Err(edges_on_left) = edges_in_row[row].binary_search(&col) else {
-                continue;
-            };
-
-            use Tile::*;
-
-            let mut is_inside = false;
-            let mut curr_edge: Tile;
-            let mut prev_edge: Tile = Ground;
-
-            for edge_idx in 0..edges_on_left {
-                curr_edge = map[row][edges_in_row[row][edge_idx]];
-
-                match curr_edge {
-                    NorthSouthPipe => is_inside = !is_inside,
-                    NorthWestPipe if prev_edge == SouthEastPipe => is_inside = !is_inside,
-                    SouthWestPipe if prev_edge == NorthEastPipe => is_inside = !is_inside,
-                    _ => (),
-                }
-
-                // Don't store the sides, we want edges
-                if curr_edge != EastWestPipe {
-                    prev_edge = curr_edge;
-                }
-            }
-
-            if is_inside {
-                count += 1;
-            }
-        }
-    }
+    let cycle = find_cycle(map, start);
+    let vertices: Vec<_> = cycle
+        .iter()
+        .map(|coord| (coord.row as i64, coord.col as i64))
+        .collect();
+
+    // Every tile on the loop is a lattice point on its boundary, so the enclosed tiles
+    // are exactly the interior points
+    let count = aoc::geometry::interior_points(&vertices);
 
     println!("{}", count);
 }
@@ -1,5 +1,7 @@
 use std::collections::{HashMap, HashSet};
 
+use thiserror::Error;
+
 struct Rule {
     before: i32,
     after: i32,
@@ -47,38 +49,125 @@ fn part_one(rules: &Vec<Rule>, orderings: &Vec<Vec<i32>>) {
     println!("{}", sum);
 }
 
-fn part_two(rules: &Vec<Rule>, orderings: &Vec<Vec<i32>>) {
-    let mut sum = 0;
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+enum OrderingError {
+    #[error("pages {} form a rule cycle", format_cycle(.0))]
+    Cycle(Vec<i32>),
+    #[error("the rules don't order pages {0} and {1}, so the middle page is ambiguous")]
+    AmbiguousMiddle(i32, i32),
+}
 
-    let comes_after = compute_adjacency_list(rules);
-    for ordering in orderings.iter() {
-        if is_valid_ordering(&comes_after, ordering) {
-            continue;
+fn format_cycle(pages: &[i32]) -> String {
+    let mut pages: Vec<_> = pages.iter().map(i32::to_string).collect();
+    pages.push(pages[0].clone());
+    pages.join(" -> ")
+}
+
+/// Sorts the pages of an update by the rules that apply to them. Fails if those rules contain a
+/// cycle, or if they don't pin down which page ends up in the middle.
+fn order_update(
+    comes_after: &HashMap<i32, Vec<i32>>,
+    ordering: &[i32],
+) -> Result<Vec<i32>, OrderingError> {
+    let pages: HashSet<i32> = ordering.iter().copied().collect();
+    // Only the rules between pages of this update apply
+    let mut after: HashMap<i32, Vec<i32>> = HashMap::new();
+    let mut before: HashMap<i32, Vec<i32>> = HashMap::new();
+    for page in ordering.iter() {
+        for next in comes_after.get(page).into_iter().flatten() {
+            if pages.contains(next) {
+                after.entry(*page).or_default().push(*next);
+                before.entry(*next).or_default().push(*page);
+            }
         }
+    }
 
-        let mut ordering = ordering.clone();
-        ordering.sort_by(|a, b| {
-            use std::cmp::Ordering;
+    // Kahn's algorithm
+    let mut in_degree: HashMap<i32, usize> = ordering
+        .iter()
+        .map(|page| (*page, before.get(page).map_or(0, Vec::len)))
+        .collect();
+    let mut to_place: Vec<i32> = ordering
+        .iter()
+        .copied()
+        .filter(|page| in_degree[page] == 0)
+        .collect();
+    let mut sorted = Vec::new();
+
+    while let Some(page) = to_place.pop() {
+        sorted.push(page);
+        for next in after.get(&page).into_iter().flatten() {
+            let degree = in_degree.get_mut(next).unwrap();
+            *degree -= 1;
+            if *degree == 0 {
+                to_place.push(*next);
+            }
+        }
+    }
 
-            let empty_after = Vec::new();
-            let after_a = comes_after.get(a).unwrap_or(&empty_after);
-            let after_b = comes_after.get(b).unwrap_or(&empty_after);
+    if sorted.len() < pages.len() {
+        // Every page left over has a page before it that is also left over, so walking backwards
+        // from any of them has to run into a cycle
+        let mut page = *ordering.iter().find(|page| in_degree[page] > 0).unwrap();
+        let mut path = Vec::new();
+        while !path.contains(&page) {
+            path.push(page);
+            page = *before[&page]
+                .iter()
+                .find(|prev| in_degree[prev] > 0)
+                .unwrap();
+        }
 
-            if after_a.contains(b) {
-                return Ordering::Less; // a comes before b
-            }
+        let start = path.iter().position(|prev| *prev == page).unwrap();
+        let mut cycle = path.split_off(start);
+        cycle.reverse();
+        return Err(OrderingError::Cycle(cycle));
+    }
 
-            if after_b.contains(a) {
-                return Ordering::Greater; // b comes before a
+    // The middle page only stays put if every other page has to go before or after it
+    let middle = sorted[sorted.len() / 2];
+    let mut related = HashSet::from([middle]);
+    for edges in [&after, &before] {
+        let mut to_visit = vec![middle];
+        while let Some(page) = to_visit.pop() {
+            for next in edges.get(&page).into_iter().flatten() {
+                if related.insert(*next) {
+                    to_visit.push(*next);
+                }
             }
+        }
+    }
+
+    if let Some(page) = ordering.iter().find(|page| !related.contains(page)) {
+        return Err(OrderingError::AmbiguousMiddle(middle, *page));
+    }
+
+    Ok(sorted)
+}
+
+fn part_two(rules: &Vec<Rule>, orderings: &Vec<Vec<i32>>) {
+    let mut sum = 0;
+    let mut errors = Vec::new();
 
-            Ordering::Equal // idc
-        });
+    let comes_after = compute_adjacency_list(rules);
+    for (idx, ordering) in orderings.iter().enumerate() {
+        if is_valid_ordering(&comes_after, ordering) {
+            continue;
+        }
 
-        sum += ordering[ordering.len() / 2];
+        match order_update(&comes_after, ordering) {
+            Ok(ordering) => sum += ordering[ordering.len() / 2],
+            Err(err) => errors.push((idx + 1, err)),
+        }
     }
 
-    println!("{}", sum);
+    if errors.is_empty() {
+        println!("{}", sum);
+    }
+
+    for (update, err) in errors {
+        eprintln!("error: update {}: {}", update, err);
+    }
 }
 
 fn main() -> std::io::Result<()> {
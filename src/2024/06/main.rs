@@ -1,161 +1,222 @@
-use std::collections::{BTreeSet, HashSet};
+use aoc::bitset::{BitGrid, DenseSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
 
-fn part_one(obstacles: &Vec<Vec<bool>>, start_pos: &(usize, usize)) {
-    let n = obstacles.len();
-    let m = obstacles[0].len();
+impl Direction {
+    fn turn_right(self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
 
-    let mut position = (start_pos.0 as isize, start_pos.1 as isize);
-    let mut direction = (-1, 0);
-    let mut visited = vec![vec![false; m]; n];
-
-    while 0 < position.0
-        && position.0 < (n - 1) as isize
-        && 0 < position.1
-        && position.1 < (m - 1) as isize
-    {
-        visited[position.0 as usize][position.1 as usize] = true;
-
-        let next_pos = (position.0 + direction.0, position.1 + direction.1);
-        if obstacles[next_pos.0 as usize][next_pos.1 as usize] {
-            direction = (direction.1, -direction.0);
-        } else {
-            position = next_pos;
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::Right => (0, 1),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
         }
     }
+}
 
-    let visit_count = visited.into_iter().flatten().filter(|v| *v).count() + 1;
-    println!("{}", visit_count);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Position {
+    row: usize,
+    col: usize,
+    dir: Direction,
 }
 
-fn part_two(obstacles: &Vec<Vec<bool>>, start_pos: &(usize, usize)) {
+/// Walks the guard until they stand on the edge of the map. Returns every tile the guard steps
+/// onto for the first time, along with where the guard was right before stepping onto it.
+fn walk_guard(obstacles: &[Vec<bool>], start_pos: &(usize, usize)) -> Vec<Position> {
     let n = obstacles.len();
     let m = obstacles[0].len();
-    let mut obstacles_in_row = vec![BTreeSet::new(); n];
-    let mut obstacles_in_col = vec![BTreeSet::new(); m];
 
-    for row in 0..n {
-        for col in 0..m {
-            if !obstacles[row][col] {
-                continue;
-            }
+    let mut pos = Position {
+        row: start_pos.0,
+        col: start_pos.1,
+        dir: Direction::Up,
+    };
+    let mut visited = BitGrid::new(n, m);
+    visited.insert(pos.row, pos.col);
+    let mut first_visits = Vec::new();
+
+    while 0 < pos.row && pos.row < n - 1 && 0 < pos.col && pos.col < m - 1 {
+        let (d_row, d_col) = pos.dir.delta();
+        let next_row = pos.row.wrapping_add_signed(d_row);
+        let next_col = pos.col.wrapping_add_signed(d_col);
+
+        if obstacles[next_row][next_col] {
+            pos.dir = pos.dir.turn_right();
+            continue;
+        }
 
-            obstacles_in_row[row].insert(col);
-            obstacles_in_col[col].insert(row);
+        if visited.insert(next_row, next_col) {
+            first_visits.push(pos);
         }
-    }
 
-    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-    enum Direction {
-        Up,
-        Right,
-        Down,
-        Left,
+        pos.row = next_row;
+        pos.col = next_col;
     }
 
-    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-    struct Position {
-        row: usize,
-        col: usize,
-        dir: Direction,
-    }
+    first_visits
+}
 
-    let compute_next_pos = |pos: Position,
-                            obstacles_in_row: &Vec<BTreeSet<usize>>,
-                            obstacles_in_col: &Vec<BTreeSet<usize>>|
-     -> Position {
-        let Position { row, col, dir } = pos;
-        match dir {
-            Direction::Up => {
-                let row = obstacles_in_col[col]
-                    .range(..row)
-                    .rev()
-                    .next()
-                    .map(|row| row + 1)
-                    .unwrap_or(0);
-                Position {
-                    row,
-                    col,
-                    dir: Direction::Right,
+/// Where the guard stops in front of the next obstacle (or the edge of the map), for every tile
+/// and direction
+struct JumpTable {
+    n: usize,
+    m: usize,
+    stops: Vec<usize>,
+}
+
+impl JumpTable {
+    fn new(obstacles: &[Vec<bool>]) -> Self {
+        let n = obstacles.len();
+        let m = obstacles[0].len();
+        let mut stops = vec![0; n * m * 4];
+        let idx = |row: usize, col: usize, dir: Direction| (row * m + col) * 4 + dir as usize;
+
+        for col in 0..m {
+            let mut stop = 0;
+            for row in 0..n {
+                stops[idx(row, col, Direction::Up)] = stop;
+                if obstacles[row][col] {
+                    stop = row + 1;
                 }
             }
-            Direction::Right => {
-                let col = obstacles_in_row[row]
-                    .range(col + 1..)
-                    .next()
-                    .map(|col| col - 1)
-                    .unwrap_or(m - 1);
-                Position {
-                    row,
-                    col,
-                    dir: Direction::Down,
+
+            let mut stop = n - 1;
+            for row in (0..n).rev() {
+                stops[idx(row, col, Direction::Down)] = stop;
+                if obstacles[row][col] {
+                    stop = row.saturating_sub(1);
                 }
             }
-            Direction::Down => {
-                let row = obstacles_in_col[col]
-                    .range(row + 1..)
-                    .next()
-                    .map(|row| row - 1)
-                    .unwrap_or(n - 1);
-                Position {
-                    row,
-                    col,
-                    dir: Direction::Left,
+        }
+
+        for row in 0..n {
+            let mut stop = 0;
+            for col in 0..m {
+                stops[idx(row, col, Direction::Left)] = stop;
+                if obstacles[row][col] {
+                    stop = col + 1;
                 }
             }
-            Direction::Left => {
-                let col = obstacles_in_row[row]
-                    .range(..col)
-                    .rev()
-                    .next()
-                    .map(|col| col + 1)
-                    .unwrap_or(0);
-                Position {
-                    row,
-                    col,
-                    dir: Direction::Up,
+
+            let mut stop = m - 1;
+            for col in (0..m).rev() {
+                stops[idx(row, col, Direction::Right)] = stop;
+                if obstacles[row][col] {
+                    stop = col.saturating_sub(1);
                 }
             }
         }
-    };
 
-    let mut sum = 0;
-    for row in 0..n {
-        for col in 0..m {
-            if obstacles[row][col] {
-                continue;
-            }
+        Self { n, m, stops }
+    }
 
-            obstacles_in_row[row].insert(col);
-            obstacles_in_col[col].insert(row);
-
-            let has_loop = 'hl: {
-                let mut path = Vec::new();
-                let mut visited = HashSet::new();
-                let mut pos = Position {
-                    row: start_pos.0,
-                    col: start_pos.1,
-                    dir: Direction::Up,
-                };
-
-                while 0 < pos.row && pos.row < n - 1 && 0 < pos.col && pos.col < m - 1 {
-                    path.push(pos);
-                    if !visited.insert(pos) {
-                        break 'hl true;
-                    }
-                    pos = compute_next_pos(pos, &obstacles_in_row, &obstacles_in_col);
-                }
+    fn state_idx(&self, pos: Position) -> usize {
+        (pos.row * self.m + pos.col) * 4 + pos.dir as usize
+    }
 
-                false
-            };
+    /// Where the guard next turns, with an extra obstacle placed at `extra`
+    fn next_pos(&self, pos: Position, extra: (usize, usize)) -> Position {
+        let Position { row, col, dir } = pos;
+        let stop = self.stops[self.state_idx(pos)];
 
-            if has_loop {
-                sum += 1;
+        let (row, col) = match dir {
+            Direction::Up if extra.1 == col && (stop..row).contains(&extra.0) => (extra.0 + 1, col),
+            Direction::Down if extra.1 == col && (row + 1..=stop).contains(&extra.0) => {
+                (extra.0 - 1, col)
             }
+            Direction::Left if extra.0 == row && (stop..col).contains(&extra.1) => {
+                (row, extra.1 + 1)
+            }
+            Direction::Right if extra.0 == row && (col + 1..=stop).contains(&extra.1) => {
+                (row, extra.1 - 1)
+            }
+            Direction::Up | Direction::Down => (stop, col),
+            Direction::Left | Direction::Right => (row, stop),
+        };
+
+        Position {
+            row,
+            col,
+            dir: dir.turn_right(),
+        }
+    }
+
+    fn has_loop(&self, from: Position, extra: (usize, usize), visited: &mut DenseSet) -> bool {
+        let (n, m) = (self.n, self.m);
+        visited.clear();
 
-            obstacles_in_row[row].remove(&col);
-            obstacles_in_col[col].remove(&row);
+        let mut pos = from;
+        while 0 < pos.row && pos.row < n - 1 && 0 < pos.col && pos.col < m - 1 {
+            if !visited.insert(self.state_idx(pos)) {
+                return true;
+            }
+            pos = self.next_pos(pos, extra);
         }
+
+        false
     }
+}
+
+fn part_one(obstacles: &[Vec<bool>], start_pos: &(usize, usize)) {
+    // The starting tile isn't part of the first visits
+    let visit_count = walk_guard(obstacles, start_pos).len() + 1;
+    println!("{}", visit_count);
+}
+
+fn part_two(obstacles: &[Vec<bool>], start_pos: &(usize, usize), threads: usize) {
+    let n = obstacles.len();
+    let m = obstacles[0].len();
+    let jumps = JumpTable::new(obstacles);
+
+    // An obstacle off the guard's path changes nothing, and one on it only changes the walk from
+    // the moment the guard would first step onto it
+    let candidates: Vec<_> = walk_guard(obstacles, start_pos)
+        .into_iter()
+        .map(|pos| {
+            let (d_row, d_col) = pos.dir.delta();
+            let extra = (
+                pos.row.wrapping_add_signed(d_row),
+                pos.col.wrapping_add_signed(d_col),
+            );
+            (pos, extra)
+        })
+        .collect();
+
+    let count_loops = |candidates: &[(Position, (usize, usize))]| {
+        let mut visited = DenseSet::new(n * m * 4);
+        candidates
+            .iter()
+            .filter(|(pos, extra)| jumps.has_loop(*pos, *extra, &mut visited))
+            .count()
+    };
+
+    let chunk_size = candidates.len().div_ceil(threads.max(1)).max(1);
+    let sum: usize = std::thread::scope(|scope| {
+        let handles: Vec<_> = candidates
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| count_loops(chunk)))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .sum()
+    });
 
     println!("{}", sum);
 }
@@ -183,7 +244,7 @@ fn main() -> std::io::Result<()> {
     }
 
     part_one(&obstacles, &start_pos);
-    part_two(&obstacles, &start_pos);
+    part_two(&obstacles, &start_pos, aoc::option("threads")?.unwrap_or(1));
 
     Ok(())
 }
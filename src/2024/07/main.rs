@@ -1,9 +1,109 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::RangeInclusive;
+
 #[derive(Debug)]
 struct Calibration {
     result: i64,
     values: Vec<i64>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Operator {
+    Add,
+    Multiply,
+    Concat,
+    Subtract,
+    Divide,
+}
+
+impl std::str::FromStr for Operator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "+" => Ok(Operator::Add),
+            "*" => Ok(Operator::Multiply),
+            "||" => Ok(Operator::Concat),
+            "-" => Ok(Operator::Subtract),
+            "/" => Ok(Operator::Divide),
+            _ => Err(format!("unknown operator `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Operator::Add => "+",
+            Operator::Multiply => "*",
+            Operator::Concat => "||",
+            Operator::Subtract => "-",
+            Operator::Divide => "/",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl Operator {
+    /// Every left operand that gives a result in `results` when combined with `value`. They
+    /// always form a single range, even when division truncates or a multiplication by zero
+    /// makes any left operand work.
+    fn left_operands(self, results: RangeInclusive<i128>, value: i64) -> RangeInclusive<i128> {
+        let (lo, hi) = (*results.start(), *results.end());
+        let value = value as i128;
+        let empty = RangeInclusive::new(1, 0);
+
+        match self {
+            Operator::Add => lo - value..=hi - value,
+            Operator::Subtract => lo + value..=hi + value,
+            Operator::Multiply => match value.signum() {
+                1 => ceil_div(lo, value)..=floor_div(hi, value),
+                -1 => -floor_div(hi, -value)..=-ceil_div(lo, -value),
+                _ if results.contains(&0) => i128::MIN..=i128::MAX,
+                _ => empty,
+            },
+            Operator::Concat => {
+                if value < 0 {
+                    return empty;
+                }
+                let value_digits = 10i128.pow(count_digits(value as i64));
+                ceil_div(lo - value, value_digits).max(0)..=floor_div(hi - value, value_digits)
+            }
+            Operator::Divide => match value.signum() {
+                // Truncating division only ever moves towards zero
+                1 => {
+                    let min_lhs = |q: i128| {
+                        if q > 0 {
+                            q * value
+                        } else {
+                            q * value - (value - 1)
+                        }
+                    };
+                    let max_lhs = |q: i128| {
+                        if q < 0 {
+                            q * value
+                        } else {
+                            q * value + (value - 1)
+                        }
+                    };
+                    min_lhs(lo)..=max_lhs(hi)
+                }
+                -1 => Operator::Divide.left_operands(-hi..=-lo, -value as i64),
+                _ => empty,
+            },
+        }
+    }
+}
+
+fn floor_div(a: i128, b: i128) -> i128 {
+    a.div_euclid(b)
+}
+
+fn ceil_div(a: i128, b: i128) -> i128 {
+    -(-a).div_euclid(b)
+}
+
 fn count_digits(mut num: i64) -> u32 {
     let mut count = 0;
     while num > 0 {
@@ -13,74 +113,136 @@ fn count_digits(mut num: i64) -> u32 {
     count.max(1)
 }
 
-fn test_calibration_recursive(
-    calibration: &Calibration,
-    result: i64,
-    value_idx: usize,
-    use_concat_op: bool,
-) -> bool {
-    let value = calibration.values[value_idx];
-    if value_idx == 0 {
-        return result == value;
-    }
+/// Largest magnitude the running value can have after each value, to prune targets that can't
+/// be reached from the left
+fn magnitude_bounds(calibration: &Calibration, operators: &[Operator]) -> Vec<u64> {
+    let mut bounds = vec![calibration.values[0].unsigned_abs()];
 
-    let mut is_possible = false;
+    for &value in calibration.values.iter().skip(1) {
+        let bound = *bounds.last().unwrap();
+        let value_abs = value.unsigned_abs();
+        let value_digits = 10u64.pow(count_digits(value));
 
-    if result % value == 0 {
-        is_possible |=
-            test_calibration_recursive(calibration, result / value, value_idx - 1, use_concat_op);
+        let next_bound = operators
+            .iter()
+            .map(|operator| match operator {
+                Operator::Add | Operator::Subtract => bound.saturating_add(value_abs),
+                Operator::Multiply => bound.saturating_mul(value_abs),
+                Operator::Concat => bound.saturating_mul(value_digits).saturating_add(value_abs),
+                Operator::Divide => bound,
+            })
+            .max()
+            .unwrap_or(0);
+        bounds.push(next_bound);
     }
 
-    // Pretty sure calibration results are never negative
-    if result >= value {
-        is_possible |=
-            test_calibration_recursive(calibration, result - value, value_idx - 1, use_concat_op);
-    }
+    bounds
+}
 
-    if use_concat_op {
-        let value_digits = 10i64.pow(count_digits(value));
-        if result % value_digits == value {
-            is_possible |= test_calibration_recursive(
-                calibration,
-                result / value_digits,
-                value_idx - 1,
-                use_concat_op,
-            );
+struct Solver<'a> {
+    calibration: &'a Calibration,
+    operators: &'a [Operator],
+    bounds: Vec<u64>,
+    // (value index, lowest result, highest result) ranges known to be unsolvable
+    dead_ends: HashSet<(usize, i128, i128)>,
+}
+
+impl Solver<'_> {
+    /// Works backwards from the results, undoing the last operator at each step. The operators
+    /// used are pushed onto `used` from last to first.
+    fn solve_recursive(
+        &mut self,
+        results: RangeInclusive<i128>,
+        value_idx: usize,
+        used: &mut Vec<Operator>,
+    ) -> bool {
+        let bound = self.bounds[value_idx] as i128;
+        let lo = (*results.start()).max(-bound);
+        let hi = (*results.end()).min(bound);
+
+        if lo > hi || self.dead_ends.contains(&(value_idx, lo, hi)) {
+            return false;
+        }
+
+        let value = self.calibration.values[value_idx];
+        if value_idx == 0 {
+            return (lo..=hi).contains(&(value as i128));
         }
-    }
 
-    is_possible
+        for &operator in self.operators.iter() {
+            let lhs = operator.left_operands(lo..=hi, value);
+            used.push(operator);
+            if self.solve_recursive(lhs, value_idx - 1, used) {
+                return true;
+            }
+            used.pop();
+        }
+
+        self.dead_ends.insert((value_idx, lo, hi));
+        false
+    }
 }
 
-fn test_calibration(calibration: &Calibration, use_concat_op: bool) -> bool {
-    test_calibration_recursive(
+/// The operators that make the values evaluate to the result from left to right, if any
+fn solve_calibration(calibration: &Calibration, operators: &[Operator]) -> Option<Vec<Operator>> {
+    let mut solver = Solver {
         calibration,
-        calibration.result,
-        calibration.values.len() - 1,
-        use_concat_op,
-    )
+        operators,
+        bounds: magnitude_bounds(calibration, operators),
+        dead_ends: HashSet::new(),
+    };
+
+    let mut used = Vec::new();
+    let result = calibration.result as i128;
+    let is_possible =
+        solver.solve_recursive(result..=result, calibration.values.len() - 1, &mut used);
+
+    used.reverse();
+    is_possible.then_some(used)
 }
 
-fn part_one(calibrations: &Vec<Calibration>) {
+fn format_expression(calibration: &Calibration, operators: &[Operator]) -> String {
+    let mut expression = calibration.values[0].to_string();
+    for (operator, value) in operators.iter().zip(calibration.values.iter().skip(1)) {
+        expression += &format!(" {} {}", operator, value);
+    }
+    expression
+}
+
+fn sum_solvable(calibrations: &[Calibration], operators: &[Operator]) -> i64 {
     let mut sum = 0;
     for calibration in calibrations.iter() {
-        if test_calibration(calibration, false) {
+        if solve_calibration(calibration, operators).is_some() {
             sum += calibration.result;
         }
     }
+    sum
+}
+
+fn part_one(calibrations: &Vec<Calibration>) {
+    let sum = sum_solvable(calibrations, &[Operator::Add, Operator::Multiply]);
 
     println!("{}", sum);
 }
 
 fn part_two(calibrations: &Vec<Calibration>) {
-    let mut sum = 0;
+    let operators = [Operator::Add, Operator::Multiply, Operator::Concat];
+    let sum = sum_solvable(calibrations, &operators);
+
+    println!("{}", sum);
+}
+
+/// Prints an expression for every line that can be solved with `operators`
+fn explain(calibrations: &[Calibration], operators: &[Operator]) {
     for calibration in calibrations.iter() {
-        if test_calibration(calibration, true) {
-            sum += calibration.result;
+        if let Some(used) = solve_calibration(calibration, operators) {
+            println!(
+                "{} = {}",
+                calibration.result,
+                format_expression(calibration, &used)
+            );
         }
     }
-
-    println!("{}", sum);
 }
 
 fn main() -> std::io::Result<()> {
@@ -99,8 +261,22 @@ fn main() -> std::io::Result<()> {
         calibrations.push(Calibration { result, values });
     }
 
-    part_one(&calibrations);
-    part_two(&calibrations);
+    match std::env::args().nth(1).as_deref() {
+        Some("explain") => {
+            let operators: String = aoc::option("operators")?.unwrap_or(String::from("+,*,||"));
+            let operators: Result<Vec<Operator>, _> =
+                operators.split(',').map(|op| op.trim().parse()).collect();
+
+            match operators {
+                Ok(operators) => explain(&calibrations, &operators),
+                Err(err) => eprintln!("error: {}", err),
+            }
+        }
+        _ => {
+            part_one(&calibrations);
+            part_two(&calibrations);
+        }
+    }
 
     Ok(())
 }
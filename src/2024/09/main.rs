@@ -1,24 +1,5 @@
-struct Space {
-    nonempty: i32,
-    empty: i32,
-}
-
-fn transform_map(map: Vec<i32>) -> Vec<Space> {
-    map.into_iter()
-        .enumerate()
-        .map(|(i, count)| match i % 2 {
-            0 => Space {
-                nonempty: count,
-                empty: 0,
-            },
-            1 => Space {
-                nonempty: 0,
-                empty: count,
-            },
-            _ => unreachable!(),
-        })
-        .collect()
-}
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 
 fn part_one(map: &Vec<i32>) {
     let mut map = map.clone();
@@ -75,58 +56,51 @@ fn part_one(map: &Vec<i32>) {
 }
 
 fn part_two(map: &Vec<i32>) {
-    let mut map = transform_map(map.clone());
-    let n = map.len();
+    // Min-heap of free span start positions, per span length (1 to 9)
+    let mut free_spans = vec![BinaryHeap::new(); 10];
+    let mut files = Vec::new();
 
-    let mut sum = 0;
     let mut idx = 0;
-    let mut left = 0;
+    for (i, &count) in map.iter().enumerate() {
+        let count = count as usize;
+        if i % 2 == 0 {
+            files.push((idx, count));
+        } else if count > 0 {
+            free_spans[count].push(Reverse(idx));
+        }
+        idx += count;
+    }
 
-    while left < n {
-        if left % 2 == 0 {
-            // Nonempty blocks
-            while map[left].nonempty > 0 {
-                let id = left / 2;
-                sum += idx * id;
-                idx += 1;
-                map[left].nonempty -= 1;
-            }
+    let mut sum: u128 = 0;
 
-            while map[left].empty > 0 {
-                idx += 1;
-                map[left].empty -= 1;
-            }
-        } else {
-            // Empty blocks
-            let mut right = n - 1;
-            if right % 2 != 0 {
-                right -= 1;
-            }
+    for (id, &(file_idx, len)) in files.iter().enumerate().rev() {
+        if len == 0 {
+            continue;
+        }
 
-            // This is O(n^2), although O(nlog(n)) is also possible using a heap
-            while left <= right {
-                if map[left].empty >= map[right].nonempty {
-                    let id = right / 2;
-                    while map[right].nonempty > 0 {
-                        sum += idx * id;
-                        idx += 1;
-                        map[right].nonempty -= 1;
-                        map[right].empty += 1;
-                        map[left].empty -= 1;
-                    }
+        // Leftmost span that fits the file, if any is left of the file
+        let fit = (len..free_spans.len())
+            .filter_map(|span_len| {
+                let &Reverse(span_idx) = free_spans[span_len].peek()?;
+                Some((span_idx, span_len))
+            })
+            .filter(|&(span_idx, _)| span_idx < file_idx)
+            .min();
+
+        let idx = match fit {
+            Some((span_idx, span_len)) => {
+                free_spans[span_len].pop();
+                if span_len > len {
+                    free_spans[span_len - len].push(Reverse(span_idx + len));
                 }
-
-                right -= 2;
+                span_idx
             }
+            None => file_idx,
+        };
 
-            while map[left].empty > 0 {
-                // sum += idx * 0
-                idx += 1;
-                map[left].empty -= 1;
-            }
-        }
-
-        left += 1;
+        // id * (idx + (idx + 1) + ... + (idx + len - 1))
+        let (id, idx, len) = (id as u128, idx as u128, len as u128);
+        sum += id * (len * idx + len * len.saturating_sub(1) / 2);
     }
 
     println!("{}", sum);
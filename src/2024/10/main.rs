@@ -1,8 +1,11 @@
+use aoc::bitset::BitGrid;
+
 fn part_one(map: &Vec<Vec<i32>>) {
     let n = map.len();
     let m = map[0].len();
 
     let mut sum = 0;
+    let mut visited = BitGrid::new(n, m);
 
     for row in 0..n {
         for col in 0..m {
@@ -11,13 +14,13 @@ fn part_one(map: &Vec<Vec<i32>>) {
             }
 
             let mut score = 0;
-            let mut visited = vec![vec![false; m]; n];
+            visited.clear();
             let mut visiting = vec![];
             visiting.push((row, col));
 
             while !visiting.is_empty() {
                 let (row, col) = visiting.pop().unwrap();
-                visited[row][col] = true;
+                visited.insert(row, col);
 
                 let height = map[row][col];
                 let row = row as isize;
@@ -31,7 +34,7 @@ fn part_one(map: &Vec<Vec<i32>>) {
                 let is_valid_idx = |row: isize, col: isize| {
                     (0..n as isize).contains(&row)
                         && (0..m as isize).contains(&col)
-                        && !visited[row as usize][col as usize]
+                        && !visited.contains(row as usize, col as usize)
                         && map[row as usize][col as usize] == height + 1
                 };
 
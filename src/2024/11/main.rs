@@ -1,67 +1,326 @@
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::time::{Duration, Instant};
 
-fn blink(num: i64) -> Vec<i64> {
+use aoc::hash::{FxBuildHasher, FxHashMap};
+use thiserror::Error;
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+enum BlinkError {
+    #[error("stone {stone} times 2024 doesn't fit in a u64")]
+    StoneOverflow { stone: u64 },
+}
+
+fn blink(num: u64) -> Result<Vec<u64>, BlinkError> {
     if num == 0 {
-        return vec![1];
+        return Ok(vec![1]);
+    }
+
+    let digits = num.ilog10() + 1;
+    if digits % 2 == 0 {
+        let half = 10u64.pow(digits / 2);
+        return Ok(vec![num / half, num % half]);
+    }
+
+    let next = num
+        .checked_mul(2024)
+        .ok_or(BlinkError::StoneOverflow { stone: num })?;
+    Ok(vec![next])
+}
+
+/// Unsigned integer of any size, for stone counts that don't fit in a u128
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct BigCount {
+    // Little-endian 64-bit limbs
+    limbs: Vec<u64>,
+}
+
+impl BigCount {
+    fn one() -> Self {
+        Self { limbs: vec![1] }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.limbs.iter().all(|&limb| limb == 0)
+    }
+
+    fn add(&mut self, other: &BigCount) {
+        if self.limbs.len() < other.limbs.len() {
+            self.limbs.resize(other.limbs.len(), 0);
+        }
+
+        let mut carry = false;
+        for (idx, limb) in self.limbs.iter_mut().enumerate() {
+            let other = other.limbs.get(idx).copied().unwrap_or(0);
+            let (sum, overflow_a) = limb.overflowing_add(other);
+            let (sum, overflow_b) = sum.overflowing_add(carry as u64);
+            *limb = sum;
+            carry = overflow_a || overflow_b;
+        }
+
+        if carry {
+            self.limbs.push(1);
+        }
+    }
+}
+
+impl fmt::Display for BigCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const CHUNK: u128 = 10_000_000_000_000_000_000; // 10^19, the largest power of 10 in a u64
+
+        // Peel off 19 decimal digits at a time with long division
+        let mut limbs = self.limbs.clone();
+        let mut chunks = Vec::new();
+        while limbs.iter().any(|&limb| limb != 0) {
+            let mut remainder = 0u128;
+            for limb in limbs.iter_mut().rev() {
+                let value = (remainder << 64) | *limb as u128;
+                *limb = (value / CHUNK) as u64;
+                remainder = value % CHUNK;
+            }
+            chunks.push(remainder as u64);
+        }
+
+        match chunks.split_last() {
+            None => write!(f, "0"),
+            Some((first, rest)) => {
+                write!(f, "{}", first)?;
+                for chunk in rest.iter().rev() {
+                    write!(f, "{:019}", chunk)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Number of stones after blinking, memoized per (stone, remaining blinks). Gives up with None as
+/// soon as a count doesn't fit in a u128.
+#[derive(Debug, Default)]
+struct StoneCounter<S = FxBuildHasher> {
+    memo: HashMap<(u64, usize), u128, S>,
+}
+
+impl<S: BuildHasher> StoneCounter<S> {
+    fn count(&mut self, stone: u64, blinks: usize) -> Result<Option<u128>, BlinkError> {
+        if blinks == 0 {
+            return Ok(Some(1));
+        }
+
+        if let Some(count) = self.memo.get(&(stone, blinks)) {
+            return Ok(Some(*count));
+        }
+
+        let mut count = 0u128;
+        for next_stone in blink(stone)? {
+            let Some(next_count) = self.count(next_stone, blinks - 1)? else {
+                return Ok(None);
+            };
+            let Some(sum) = count.checked_add(next_count) else {
+                return Ok(None);
+            };
+            count = sum;
+        }
+
+        self.memo.insert((stone, blinks), count);
+        Ok(Some(count))
     }
 
-    let num_str = num.to_string();
-    let n = num_str.len();
-    if n % 2 == 0 {
-        let left: i64 = num_str[..n / 2].parse().unwrap();
-        let right: i64 = num_str[n / 2..].parse().unwrap();
-        return vec![left, right];
+    fn count_all(&mut self, stones: &[u64], blinks: usize) -> Result<Option<u128>, BlinkError> {
+        let mut count = 0u128;
+        for stone in stones.iter() {
+            let Some(stone_count) = self.count(*stone, blinks)? else {
+                return Ok(None);
+            };
+            let Some(sum) = count.checked_add(stone_count) else {
+                return Ok(None);
+            };
+            count = sum;
+        }
+        Ok(Some(count))
     }
+}
 
-    vec![num * 2024]
+/// Every stone value seen so far, numbered in the order they show up, along with what each of
+/// them turns into. There are only a few thousand distinct values, however many blinks.
+#[derive(Debug, Default)]
+struct StoneGraph {
+    ids: FxHashMap<u64, usize>,
+    values: Vec<u64>,
+    next: Vec<Option<Vec<usize>>>,
 }
 
-fn part_one(nums: &Vec<i64>) {
-    let mut nums = nums.clone();
+impl StoneGraph {
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn id(&mut self, stone: u64) -> usize {
+        *self.ids.entry(stone).or_insert_with(|| {
+            self.values.push(stone);
+            self.next.push(None);
+            self.values.len() - 1
+        })
+    }
+
+    /// The ids of the stones that stone `id` turns into after one blink
+    fn next(&mut self, id: usize) -> Result<&[usize], BlinkError> {
+        if self.next[id].is_none() {
+            let next_ids = blink(self.values[id])?
+                .into_iter()
+                .map(|stone| self.id(stone))
+                .collect();
+            self.next[id] = Some(next_ids);
+        }
+        Ok(self.next[id].as_deref().unwrap())
+    }
+}
+
+/// Number of stones after blinking, for counts too large for StoneCounter. Keeps how many stones
+/// share each value, and blinks every value once per blink.
+fn count_big(stones: &[u64], blinks: usize) -> Result<BigCount, BlinkError> {
+    let mut graph = StoneGraph::default();
+    let mut counts = Vec::new();
+    for &stone in stones.iter() {
+        let id = graph.id(stone);
+        counts.resize(graph.len(), BigCount::default());
+        counts[id].add(&BigCount::one());
+    }
+
+    let mut next_counts: Vec<BigCount> = Vec::new();
+    for _ in 0..blinks {
+        for count in next_counts.iter_mut() {
+            count.limbs.fill(0);
+        }
+
+        for (id, count) in counts.iter().enumerate() {
+            if count.is_zero() {
+                continue;
+            }
+            for &next_id in graph.next(id)? {
+                if next_id >= next_counts.len() {
+                    next_counts.resize(next_id + 1, BigCount::default());
+                }
+                next_counts[next_id].add(count);
+            }
+        }
 
-    for _ in 0..25 {
-        nums = nums.into_iter().flat_map(blink).collect();
+        std::mem::swap(&mut counts, &mut next_counts);
     }
 
-    println!("{}", nums.len());
+    let mut total = BigCount::default();
+    for count in counts.iter() {
+        total.add(count);
+    }
+    Ok(total)
 }
 
-fn part_two(nums: &Vec<i64>) {
-    let mut freqs: HashMap<i64, i64> = Default::default();
-    for num in nums.iter() {
-        *freqs.entry(*num).or_insert(0) += 1;
+/// Number of stones after blinking, as a decimal string
+fn count_stones(stones: &[u64], blinks: usize) -> Result<String, BlinkError> {
+    match StoneCounter::<FxBuildHasher>::default().count_all(stones, blinks)? {
+        Some(count) => Ok(count.to_string()),
+        None => Ok(count_big(stones, blinks)?.to_string()),
     }
+}
 
-    for _ in 0..75 {
-        let mut next_freqs = HashMap::new();
+/// Number of distinct stone values after blinking, and over every blink up to then
+fn count_distinct(stones: &[u64], blinks: usize) -> Result<(usize, usize), BlinkError> {
+    let mut graph = StoneGraph::default();
+    let mut ids: Vec<usize> = stones.iter().map(|&stone| graph.id(stone)).collect();
+    ids.sort_unstable();
+    ids.dedup();
 
-        for (num, freq) in freqs.iter() {
-            for num in blink(*num) {
-                *next_freqs.entry(num).or_insert(0) += freq;
+    for _ in 0..blinks {
+        let mut in_next = vec![false; graph.len()];
+        let mut next_ids = Vec::new();
+        for &id in ids.iter() {
+            for &next_id in graph.next(id)? {
+                if next_id >= in_next.len() {
+                    in_next.resize(next_id + 1, false);
+                }
+                if !in_next[next_id] {
+                    in_next[next_id] = true;
+                    next_ids.push(next_id);
+                }
             }
         }
+        ids = next_ids;
+    }
+
+    // Only values that show up get blinked, so the graph holds exactly the values seen
+    Ok((ids.len(), graph.len()))
+}
 
-        freqs = next_freqs;
+fn print_count(stones: &[u64], blinks: usize) {
+    match count_stones(stones, blinks) {
+        Ok(count) => println!("{}", count),
+        Err(err) => eprintln!("error: {}", err),
     }
+}
+
+fn part_one(nums: &[u64]) {
+    print_count(nums, 25);
+}
+
+fn part_two(nums: &[u64]) {
+    print_count(nums, 75);
+}
 
-    let mut count = 0;
-    for freq in freqs.values() {
-        count += freq;
+fn print_stats(nums: &[u64], blinks: usize) {
+    let stats = count_stones(nums, blinks)
+        .and_then(|count| Ok((count, count_distinct(nums, blinks)?)));
+
+    match stats {
+        Ok((count, (distinct, seen))) => {
+            println!("stones after {} blinks: {}", blinks, count);
+            println!("distinct values after {} blinks: {}", blinks, distinct);
+            println!("distinct values over all blinks: {}", seen);
+        }
+        Err(err) => eprintln!("error: {}", err),
     }
+}
 
-    println!("{}", count);
+/// Average time to count the stones after `blinks`, with the memo using the hasher `S`
+fn time_count<S: BuildHasher + Default>(stones: &[u64], blinks: usize, runs: u32) -> Duration {
+    let start = Instant::now();
+    for _ in 0..runs {
+        let _ = std::hint::black_box(StoneCounter::<S>::default().count_all(stones, blinks));
+    }
+    start.elapsed() / runs.max(1)
+}
+
+/// Compares the std SipHash memo against the FxHash one
+fn bench(stones: &[u64], blinks: usize, runs: u32) {
+    let sip = time_count::<RandomState>(stones, blinks, runs);
+    let fx = time_count::<FxBuildHasher>(stones, blinks, runs);
+
+    println!("{} blinks, average of {} runs", blinks, runs);
+    println!("std (SipHash): {:?}", sip);
+    println!("FxHash:        {:?}", fx);
 }
 
 fn main() -> std::io::Result<()> {
     let input = aoc::read_input(2024, 11)?;
 
-    let nums: Vec<i64> = input
+    let nums: Vec<u64> = input
         .split_whitespace()
         .map(|num| num.parse().unwrap())
         .collect();
 
-    part_one(&nums);
-    part_two(&nums);
+    match std::env::args().nth(1).as_deref() {
+        Some("stats") => print_stats(&nums, aoc::option("blinks")?.unwrap_or(75)),
+        Some("bench") => bench(
+            &nums,
+            aoc::option("blinks")?.unwrap_or(75),
+            aoc::option("runs")?.unwrap_or(20),
+        ),
+        _ => {
+            part_one(&nums);
+            part_two(&nums);
+        }
+    }
 
     Ok(())
 }
@@ -10,25 +10,53 @@ struct Robot {
     velocity: Coord,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Room {
+    width: i64,
+    height: i64,
+}
+
 const SECONDS: i64 = 100;
-const WIDTH: i64 = 101;
-const HEIGHT: i64 = 103;
+const ROOM: Room = Room {
+    width: 101,
+    height: 103,
+};
+const EXAMPLE_ROOM: Room = Room {
+    width: 11,
+    height: 7,
+};
 
-fn part_one(robots: &Vec<Robot>) {
+/// The room size isn't part of the input, but the example is the only input that fits in 11x7
+fn detect_room(robots: &[Robot]) -> Room {
+    let fits_example = robots.iter().all(|robot| {
+        (0..EXAMPLE_ROOM.width).contains(&robot.position.x)
+            && (0..EXAMPLE_ROOM.height).contains(&robot.position.y)
+    });
+
+    if fits_example {
+        EXAMPLE_ROOM
+    } else {
+        ROOM
+    }
+}
+
+fn part_one(robots: &Vec<Robot>, room: Room, seconds: i64) {
     let mut robots = robots.clone();
+    let Room { width, height } = room;
 
     for robot in robots.iter_mut() {
-        robot.position.x += SECONDS * robot.velocity.x;
-        robot.position.y += SECONDS * robot.velocity.y;
+        robot.position.x += seconds * robot.velocity.x;
+        robot.position.y += seconds * robot.velocity.y;
 
-        robot.position.x = (robot.position.x % WIDTH + WIDTH) % WIDTH;
-        robot.position.y = (robot.position.y % HEIGHT + HEIGHT) % HEIGHT;
+        robot.position.x = (robot.position.x % width + width) % width;
+        robot.position.y = (robot.position.y % height + height) % height;
     }
 
-    let left_cutoff = WIDTH / 2;
-    let right_cutoff = (WIDTH - 1) / 2;
-    let top_cutoff = HEIGHT / 2;
-    let bottom_cutoff = (HEIGHT - 1) / 2;
+    // Robots exactly in the middle don't count, and there is no middle in an even-sized room
+    let left_cutoff = width / 2;
+    let right_cutoff = (width - 1) / 2;
+    let top_cutoff = height / 2;
+    let bottom_cutoff = (height - 1) / 2;
 
     let mut top_left = 0;
     let mut top_right = 0;
@@ -62,20 +90,88 @@ fn part_one(robots: &Vec<Robot>) {
     println!("{}", safety_factor);
 }
 
-fn part_two(robots: &Vec<Robot>) {
+/// Variance of the robots' positions along one axis, times the number of robots squared
+fn spread(values: impl Iterator<Item = i64> + Clone) -> i64 {
+    let count = values.clone().count() as i64;
+    let sum: i64 = values.clone().sum();
+    let sum_squares: i64 = values.map(|value| value * value).sum();
+    count * sum_squares - sum * sum
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// The tightest frames along x and along y, and the first time both happen at once. The frames
+/// repeat every lcm(width, height) seconds, and if the sizes share a factor the two might never
+/// line up.
+fn find_easter_egg(robots: &[Robot], room: Room) -> (i64, i64, Option<i64>) {
+    let Room { width, height } = room;
+
+    // The picture is drawn by most robots bunching up, which happens in x every width seconds and
+    // in y every height seconds. Find the tightest frame along each axis separately...
+    let position_after = |position: i64, velocity: i64, time: i64, size: i64| {
+        (position + time * velocity).rem_euclid(size)
+    };
+
+    let time_x = (0..width).min_by_key(|&time| {
+        spread(
+            robots
+                .iter()
+                .map(move |robot| position_after(robot.position.x, robot.velocity.x, time, width)),
+        )
+    });
+    let time_y = (0..height).min_by_key(|&time| {
+        spread(
+            robots
+                .iter()
+                .map(move |robot| position_after(robot.position.y, robot.velocity.y, time, height)),
+        )
+    });
+    let time_x = time_x.expect("room is at least 1 wide");
+    let time_y = time_y.expect("room is at least 1 high");
+
+    // ...then combine them with the chinese remainder theorem:
+    // time = time_x (mod width), time = time_y (mod height)
+    let period = width / gcd(width, height) * height;
+    let time = (0..period / width)
+        .map(|k| time_x + k * width)
+        .find(|time| time % height == time_y);
+
+    (time_x, time_y, time)
+}
+
+fn part_two(robots: &Vec<Robot>, room: Room) {
+    let Room { width, height } = room;
+    match find_easter_egg(robots, room) {
+        (_, _, Some(time)) => println!("{}", time),
+        (time_x, time_y, None) => eprintln!(
+            "error: the tightest frames along x (time = {} mod {}) and y (time = {} mod {}) never \
+             happen at once",
+            time_x, width, time_y, height
+        ),
+    }
+}
+
+fn animate(robots: &Vec<Robot>, room: Room) {
     let mut robots = robots.clone();
+    let Room { width, height } = room;
 
     let mut time = 0;
     loop {
         time += 1;
 
         for robot in robots.iter_mut() {
-            robot.position.x = ((robot.position.x + robot.velocity.x) % WIDTH + WIDTH) % WIDTH;
-            robot.position.y = ((robot.position.y + robot.velocity.y) % HEIGHT + HEIGHT) % HEIGHT;
+            robot.position.x = ((robot.position.x + robot.velocity.x) % width + width) % width;
+            robot.position.y = ((robot.position.y + robot.velocity.y) % height + height) % height;
         }
 
-        let rows = HEIGHT as usize;
-        let cols = WIDTH as usize;
+        let rows = height as usize;
+        let cols = width as usize;
         let mut map = vec![vec![false; cols]; rows];
 
         for robot in robots.iter() {
@@ -86,7 +182,7 @@ fn part_two(robots: &Vec<Robot>) {
 
         // Just check for a grid I guess lmao
 
-        println!("{}", "\n".repeat(HEIGHT as usize));
+        println!("{}", "\n".repeat(height as usize));
         print!("\x1B[1;1H"); // positions the cursor at 1,1
         println!("After {} seconds:", time);
         for row in 0..rows {
@@ -124,8 +220,39 @@ fn main() -> std::io::Result<()> {
         robots.push(robot);
     }
 
-    part_one(&robots);
-    part_two(&robots);
+    let detected = detect_room(&robots);
+    let room = Room {
+        width: aoc::option("width")?.unwrap_or(detected.width),
+        height: aoc::option("height")?.unwrap_or(detected.height),
+    };
+    let seconds = aoc::option("seconds")?.unwrap_or(SECONDS);
+
+    if room.width <= 0 || room.height <= 0 {
+        eprintln!(
+            "error: the room must be at least 1x1, not {}x{}",
+            room.width, room.height
+        );
+        return Ok(());
+    }
+    let outside = robots.iter().find(|robot| {
+        !(0..room.width).contains(&robot.position.x)
+            || !(0..room.height).contains(&robot.position.y)
+    });
+    if let Some(robot) = outside {
+        eprintln!(
+            "error: robot at {},{} is outside the {}x{} room",
+            robot.position.x, robot.position.y, room.width, room.height
+        );
+        return Ok(());
+    }
+
+    match std::env::args().nth(1).as_deref() {
+        Some("animate") => animate(&robots, room),
+        _ => {
+            part_one(&robots, room, seconds);
+            part_two(&robots, room);
+        }
+    }
 
     Ok(())
 }
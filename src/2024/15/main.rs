@@ -1,10 +1,15 @@
 use std::collections::HashSet;
+use std::fmt;
+
+use thiserror::Error;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Tile {
     Empty,
     Wall,
     Box,
+    BoxLeft,
+    BoxRight,
     Robot,
 }
 
@@ -16,208 +21,289 @@ enum Move {
     Right,
 }
 
+impl Move {
+    fn dir(self) -> Coord {
+        match self {
+            Move::Up => Coord { row: -1, col: 0 },
+            Move::Down => Coord { row: 1, col: 0 },
+            Move::Left => Coord { row: 0, col: -1 },
+            Move::Right => Coord { row: 0, col: 1 },
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 struct Coord {
     row: isize,
     col: isize,
 }
 
-fn find_robot(map: &Vec<Vec<Tile>>) -> Coord {
-    for (row, tiles) in map.iter().enumerate() {
-        for (col, tile) in tiles.iter().enumerate() {
-            if tile == &Tile::Robot {
-                return Coord {
-                    row: row as isize,
-                    col: col as isize,
-                };
-            }
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+enum InvariantError {
+    #[error("box half at row {row}, col {col} has no other half")]
+    Unpaired { row: usize, col: usize },
+    #[error("{found} boxes in the warehouse, expected {expected}")]
+    BoxCount { expected: usize, found: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Warehouse {
+    map: Vec<Vec<Tile>>,
+    robot: Coord,
+}
+
+impl Warehouse {
+    fn new(map: &Vec<Vec<Tile>>) -> Self {
+        Self {
+            map: map.clone(),
+            robot: find_robot(map),
         }
     }
-    unreachable!()
-}
 
-fn part_one(map: &Vec<Vec<Tile>>, moves: &Vec<Move>) {
-    let mut map = map.clone();
-
-    let mut robot = find_robot(&map);
-    let mut move_robot = |dir: Coord| {
-        let boxes_to_move = {
-            let mut count = 0;
-            let mut row = robot.row;
-            let mut col = robot.col;
-
-            loop {
-                row += dir.row;
-                col += dir.col;
-                if map[row as usize][col as usize] != Tile::Box {
-                    break;
+    /// Everything except the robot is twice as wide
+    fn widen(map: &[Vec<Tile>]) -> Self {
+        let map: Vec<Vec<Tile>> = map
+            .iter()
+            .map(|tiles| {
+                tiles
+                    .iter()
+                    .flat_map(|tile| match tile {
+                        Tile::Box => [Tile::BoxLeft, Tile::BoxRight],
+                        Tile::Robot => [Tile::Robot, Tile::Empty],
+                        tile => [*tile, *tile],
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            robot: find_robot(&map),
+            map,
+        }
+    }
+
+    fn get(&self, coord: Coord) -> Tile {
+        self.map[coord.row as usize][coord.col as usize]
+    }
+
+    fn set(&mut self, coord: Coord, tile: Tile) {
+        self.map[coord.row as usize][coord.col as usize] = tile;
+    }
+
+    /// Moves the robot, pushing every box in the way. Returns false if a wall blocks the push.
+    fn step(&mut self, mov: Move) -> bool {
+        let dir = mov.dir();
+
+        let mut to_move = Vec::new();
+        let mut seen = HashSet::new();
+        let mut to_visit = vec![self.robot];
+        seen.insert(self.robot);
+
+        while let Some(coord) = to_visit.pop() {
+            to_move.push((coord, self.get(coord)));
+
+            let next = Coord {
+                row: coord.row + dir.row,
+                col: coord.col + dir.col,
+            };
+
+            // Also push the other half of wide boxes, which only matters when moving vertically
+            let mut pushed = vec![next];
+            match self.get(next) {
+                Tile::Wall => return false,
+                Tile::Empty | Tile::Robot => pushed.clear(),
+                Tile::Box => (),
+                Tile::BoxLeft => pushed.push(Coord {
+                    col: next.col + 1,
+                    ..next
+                }),
+                Tile::BoxRight => pushed.push(Coord {
+                    col: next.col - 1,
+                    ..next
+                }),
+            }
+
+            for coord in pushed {
+                if seen.insert(coord) {
+                    to_visit.push(coord);
                 }
-                count += 1;
             }
+        }
 
-            count
-        };
+        for (coord, _) in to_move.iter() {
+            self.set(*coord, Tile::Empty);
+        }
+        for (coord, tile) in to_move.iter() {
+            let next = Coord {
+                row: coord.row + dir.row,
+                col: coord.col + dir.col,
+            };
+            self.set(next, *tile);
+        }
 
-        let is_path_blocked = {
-            let row = robot.row + dir.row * (boxes_to_move + 1);
-            let col = robot.col + dir.col * (boxes_to_move + 1);
-            map[row as usize][col as usize] == Tile::Wall
-        };
+        self.robot.row += dir.row;
+        self.robot.col += dir.col;
 
-        if is_path_blocked {
-            return;
-        }
+        true
+    }
+
+    fn box_count(&self) -> usize {
+        self.map
+            .iter()
+            .flatten()
+            .filter(|tile| matches!(tile, Tile::Box | Tile::BoxLeft))
+            .count()
+    }
 
-        let mut row = robot.row;
-        let mut col = robot.col;
-        map[row as usize][col as usize] = Tile::Empty;
+    /// Every `[` is directly followed by a `]`, and there are still `boxes` boxes
+    fn check_invariants(&self, boxes: usize) -> Result<(), InvariantError> {
+        for (row, tiles) in self.map.iter().enumerate() {
+            for (col, tile) in tiles.iter().enumerate() {
+                let paired = match tile {
+                    Tile::BoxLeft => tiles.get(col + 1) == Some(&Tile::BoxRight),
+                    Tile::BoxRight => col > 0 && tiles[col - 1] == Tile::BoxLeft,
+                    _ => true,
+                };
 
-        row += dir.row;
-        col += dir.col;
-        map[row as usize][col as usize] = Tile::Robot;
+                if !paired {
+                    return Err(InvariantError::Unpaired { row, col });
+                }
+            }
+        }
 
-        if boxes_to_move > 0 {
-            row += dir.row * boxes_to_move;
-            col += dir.col * boxes_to_move;
-            map[row as usize][col as usize] = Tile::Box;
+        let found = self.box_count();
+        if found != boxes {
+            return Err(InvariantError::BoxCount {
+                expected: boxes,
+                found,
+            });
         }
 
-        robot.row += dir.row;
-        robot.col += dir.col;
-    };
+        Ok(())
+    }
 
-    for mov in moves.iter() {
-        match mov {
-            Move::Up => move_robot(Coord { row: -1, col: 0 }),
-            Move::Down => move_robot(Coord { row: 1, col: 0 }),
-            Move::Left => move_robot(Coord { row: 0, col: -1 }),
-            Move::Right => move_robot(Coord { row: 0, col: 1 }),
+    fn gps_sum(&self) -> usize {
+        let mut sum = 0;
+        for (row, tiles) in self.map.iter().enumerate() {
+            for (col, tile) in tiles.iter().enumerate() {
+                if matches!(tile, Tile::Box | Tile::BoxLeft) {
+                    sum += 100 * row + col;
+                }
+            }
         }
+        sum
     }
+}
 
-    let mut sum = 0;
-    for (row, tiles) in map.iter().enumerate() {
-        for (col, tile) in tiles.iter().enumerate() {
-            if tile == &Tile::Box {
-                sum += 100 * row + col;
+impl fmt::Display for Warehouse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for tiles in self.map.iter() {
+            for tile in tiles.iter() {
+                let c = match tile {
+                    Tile::Empty => '.',
+                    Tile::Wall => '#',
+                    Tile::Box => 'O',
+                    Tile::BoxLeft => '[',
+                    Tile::BoxRight => ']',
+                    Tile::Robot => '@',
+                };
+                write!(f, "{}", c)?;
             }
+            writeln!(f)?;
         }
+        Ok(())
     }
+}
 
-    println!("{}", sum);
+/// Steps through the moves one at a time, lending out the warehouse after each of them
+struct Simulation<'a> {
+    warehouse: Warehouse,
+    moves: &'a [Move],
+    idx: usize,
 }
 
-fn part_two(map: &Vec<Vec<Tile>>, moves: &Vec<Move>) {
-    let mut robot = find_robot(map);
-    let mut walls = HashSet::new();
-    let mut boxes = HashSet::new();
+impl<'a> Simulation<'a> {
+    fn new(warehouse: Warehouse, moves: &'a [Move]) -> Self {
+        Self {
+            warehouse,
+            moves,
+            idx: 0,
+        }
+    }
+
+    fn next_step(&mut self) -> Option<(Move, &Warehouse)> {
+        let mov = *self.moves.get(self.idx)?;
+        self.idx += 1;
+        self.warehouse.step(mov);
+        Some((mov, &self.warehouse))
+    }
 
-    // Expand map
-    robot.col *= 2;
+    fn run(mut self) -> Warehouse {
+        while self.next_step().is_some() {}
+        self.warehouse
+    }
+}
 
+fn find_robot(map: &Vec<Vec<Tile>>) -> Coord {
     for (row, tiles) in map.iter().enumerate() {
         for (col, tile) in tiles.iter().enumerate() {
-            let coord = Coord {
-                row: row as isize,
-                col: col as isize * 2,
-            };
-            match tile {
-                Tile::Wall => {
-                    walls.insert(coord);
-                }
-                Tile::Box => {
-                    boxes.insert(coord);
-                }
-                _ => (),
+            if tile == &Tile::Robot {
+                return Coord {
+                    row: row as isize,
+                    col: col as isize,
+                };
             }
         }
     }
+    unreachable!()
+}
 
-    // Move robot
-    let mut move_robot = |dir: Coord| {
-        let mut boxes_to_move = HashSet::new();
-        let mut coords_to_move = Vec::new();
-        coords_to_move.push(Coord {
-            row: robot.row + dir.row,
-            col: robot.col + dir.col,
-        });
-
-        while !coords_to_move.is_empty() {
-            // Also check coordinate to the left to account for expanded tiles
-            let coord = coords_to_move.pop().unwrap();
-            let coord2 = Coord {
-                row: coord.row,
-                col: coord.col - 1,
-            };
+fn part_one(map: &Vec<Vec<Tile>>, moves: &Vec<Move>) {
+    let warehouse = Simulation::new(Warehouse::new(map), moves).run();
 
-            if boxes_to_move.contains(&coord) || boxes_to_move.contains(&coord2) {
-                continue;
-            }
+    println!("{}", warehouse.gps_sum());
+}
 
-            // Check if the path is blocked
-            let is_wall = walls.contains(&coord) || walls.contains(&coord2);
-            if is_wall {
-                return;
-            }
+fn part_two(map: &Vec<Vec<Tile>>, moves: &Vec<Move>) {
+    let warehouse = Simulation::new(Warehouse::widen(map), moves).run();
 
-            let is_box_right = boxes.contains(&coord);
-            let is_box_left = boxes.contains(&coord2);
-            let is_box = is_box_left || is_box_right;
-            if is_box {
-                let box_left = if is_box_left { coord2 } else { coord };
-                let box_right = Coord {
-                    row: box_left.row,
-                    col: box_left.col + 1,
-                };
+    println!("{}", warehouse.gps_sum());
+}
 
-                boxes_to_move.insert(box_left);
+/// Runs every move, checking the invariants after each of them
+fn check(warehouse: Warehouse, moves: &[Move]) -> Result<(), (usize, InvariantError)> {
+    let boxes = warehouse.box_count();
+    let mut simulation = Simulation::new(warehouse, moves);
+
+    let mut step = 0;
+    while let Some((_, warehouse)) = simulation.next_step() {
+        step += 1;
+        warehouse
+            .check_invariants(boxes)
+            .map_err(|err| (step, err))?;
+    }
 
-                coords_to_move.push(Coord {
-                    row: box_left.row + dir.row,
-                    col: box_left.col + dir.col,
-                });
+    Ok(())
+}
 
-                coords_to_move.push(Coord {
-                    row: box_right.row + dir.row,
-                    col: box_right.col + dir.col,
-                });
-            }
-        }
+/// Prints the warehouse after every move, at `speed` moves per second
+fn replay(warehouse: Warehouse, moves: &[Move], speed: f64) {
+    // Speeds so slow that the delay overflows a Duration just wait forever
+    let delay =
+        std::time::Duration::try_from_secs_f64(1.0 / speed).unwrap_or(std::time::Duration::MAX);
+    let mut simulation = Simulation::new(warehouse, moves);
 
-        // Move all boxes
-        let boxes_to_move = Vec::from_iter(boxes_to_move);
-        for box_to_move in boxes_to_move.iter() {
-            boxes.remove(box_to_move);
-        }
-        for box_to_move in boxes_to_move.iter() {
-            boxes.insert(Coord {
-                row: box_to_move.row + dir.row,
-                col: box_to_move.col + dir.col,
-            });
-        }
+    let mut step = 0;
+    while let Some((mov, warehouse)) = simulation.next_step() {
+        step += 1;
 
-        // Move robot
-        robot.row += dir.row;
-        robot.col += dir.col;
-    };
-
-    for mov in moves.iter() {
-        match mov {
-            Move::Up => move_robot(Coord { row: -1, col: 0 }),
-            Move::Down => move_robot(Coord { row: 1, col: 0 }),
-            Move::Left => move_robot(Coord { row: 0, col: -1 }),
-            Move::Right => move_robot(Coord { row: 0, col: 1 }),
-        }
-    }
+        println!("{}", "\n".repeat(warehouse.map.len()));
+        print!("\x1B[1;1H"); // positions the cursor at 1,1
+        println!("Move {}/{}: {:?}", step, moves.len(), mov);
+        print!("{}", warehouse);
 
-    // Calculate GPS sum
-    let mut sum = 0;
-    for bx in boxes.iter() {
-        // bro since when was box a reserved name
-        sum += 100 * bx.row + bx.col;
+        std::thread::sleep(delay);
     }
-
-    println!("{}", sum);
 }
 
 fn main() -> std::io::Result<()> {
@@ -253,8 +339,39 @@ fn main() -> std::io::Result<()> {
         })
         .collect();
 
-    part_one(&map, &moves);
-    part_two(&map, &moves);
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("replay") => {
+            let warehouse = match args.next().as_deref() {
+                Some("wide") => Warehouse::widen(&map),
+                _ => Warehouse::new(&map),
+            };
+            let speed: f64 = aoc::option("speed")?.unwrap_or(20.0);
+            if !speed.is_finite() || speed <= 0.0 {
+                eprintln!(
+                    "error: speed must be a positive number of moves per second, not {}",
+                    speed
+                );
+                return Ok(());
+            }
+            replay(warehouse, &moves, speed);
+        }
+        Some("check") => {
+            for (name, warehouse) in [
+                ("part one", Warehouse::new(&map)),
+                ("part two", Warehouse::widen(&map)),
+            ] {
+                match check(warehouse, &moves) {
+                    Ok(()) => println!("{}: ok", name),
+                    Err((step, err)) => println!("{}: after move {}: {}", name, step, err),
+                }
+            }
+        }
+        _ => {
+            part_one(&map, &moves);
+            part_two(&map, &moves);
+        }
+    }
 
     Ok(())
 }
@@ -1,5 +1,9 @@
+use aoc::bitset::BitGrid;
 use std::cmp::Reverse;
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::collections::BinaryHeap;
+use std::io::Write;
+
+use thiserror::Error;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Tile {
@@ -38,133 +42,127 @@ fn find_start_and_end(map: &Vec<Vec<Tile>>) -> (Vec3, Vec3) {
     (start, end)
 }
 
-const MOVE_COST: i32 = 1;
-const TURN_COST: i32 = 1000;
-
-fn compute_dijkstras(map: &Vec<Vec<Tile>>, start: Vec3) -> Vec<Vec<i32>> {
-    let n = map.len();
-    let m = map[0].len();
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Costs {
+    step: u32,
+    turn: u32,
+}
 
-    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
-    struct Node {
-        score: i32,
-        pos: Vec3,
-        dir: Vec3,
-    }
+const COSTS: Costs = Costs {
+    step: 1,
+    turn: 1000,
+};
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+enum MazeError {
+    #[error("path score overflows u64")]
+    Overflow,
+    #[error("the end can't be reached")]
+    Unreachable,
+}
 
-    impl PartialOrd for Node {
-        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-            self.score.partial_cmp(&other.score)
-        }
-    }
+/// State used in compute_dijkstras_with_state()
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
+struct State {
+    pos: Vec3,
+    dir: Vec3,
+}
 
-    impl Ord for Node {
-        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-            self.score.cmp(&other.score)
+impl State {
+    /// Which of the four direction layers the state is on
+    fn layer(&self) -> usize {
+        match (self.dir.x, self.dir.y) {
+            (1, 0) => 0,
+            (0, 1) => 1,
+            (-1, 0) => 2,
+            (0, -1) => 3,
+            _ => unreachable!(),
         }
     }
+}
 
-    let mut min_scores = vec![vec![i32::MAX; m]; n];
-    min_scores[start.y as usize][start.x as usize] = 0;
-
-    let mut to_process = BinaryHeap::new();
-    to_process.push(Reverse(Node {
-        score: 0,
-        pos: start,
-        dir: Vec3 { x: 1, y: 0 },
-    }));
-
-    while !to_process.is_empty() {
-        let Node { score, pos, dir } = to_process.pop().unwrap().0;
-
-        let dir_left = Vec3 {
-            x: -dir.y,
-            y: dir.x,
-        };
-        let dir_right = Vec3 {
-            x: dir.y,
-            y: -dir.x,
-        };
-        let dir_back = Vec3 {
-            x: -dir.x,
-            y: -dir.y,
-        };
-
-        let mut try_move = |dir: Vec3, cost: i32| {
-            let prev = pos;
-            let pos = Vec3 {
-                x: prev.x + dir.x,
-                y: prev.y + dir.y,
-            };
-            let x = pos.x as usize;
-            let y = pos.y as usize;
-            let score = score + cost;
-
-            if map[y][x] != Tile::Wall {
-                if score < min_scores[y][x] {
-                    min_scores[y][x] = score;
-                    to_process.push(Reverse(Node { score, pos, dir }));
-                }
-            }
-        };
+/// The reindeer starts facing east
+const START_DIR: Vec3 = Vec3 { x: 1, y: 0 };
+
+const DIRECTIONS: [Vec3; 4] = [
+    Vec3 { x: 1, y: 0 },
+    Vec3 { x: 0, y: 1 },
+    Vec3 { x: -1, y: 0 },
+    Vec3 { x: 0, y: -1 },
+];
+
+/// Minimum score of every state, and the previous states on its minimum paths, stored densely by
+/// (row, col, direction)
+struct StateScores {
+    m: usize,
+    min_scores: Vec<u64>,
+    min_paths: Vec<Vec<State>>,
+}
 
-        try_move(dir, MOVE_COST);
-        try_move(dir_left, TURN_COST + MOVE_COST);
-        try_move(dir_right, TURN_COST + MOVE_COST);
-        try_move(dir_back, 2 * TURN_COST + MOVE_COST);
+impl StateScores {
+    fn idx(&self, state: State) -> usize {
+        (state.pos.y as usize * self.m + state.pos.x as usize) * 4 + state.layer()
     }
 
-    min_scores
-}
+    fn score(&self, state: State) -> u64 {
+        self.min_scores[self.idx(state)]
+    }
 
-/// State used in compute_dijkstras_with_state()
-#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
-struct State {
-    pos: Vec3,
-    dir: Vec3,
+    fn prev(&self, state: State) -> &[State] {
+        &self.min_paths[self.idx(state)]
+    }
 }
 
-/// Because (1,1,EAST) != (1,1,WEST), the optimized dijkstra's algorithm above, although working for
-/// computing the minimum cost of the shortest path, will not work for computing what the shortest
-/// path itself is (well, it does work, but only for a single path - it will fail if there are
-/// multiple shortest paths)
-fn compute_dijkstras_with_state(map: &Vec<Vec<Tile>>, start: Vec3) -> HashMap<State, Vec<State>> {
+/// Dijkstra's over (position, direction) states, since (1,1,EAST) != (1,1,WEST): the cheapest way
+/// onto a tile isn't always part of the cheapest path through it, and every best path is kept
+fn compute_dijkstras_with_state(
+    map: &Vec<Vec<Tile>>,
+    start: Vec3,
+    costs: Costs,
+) -> Result<StateScores, MazeError> {
+    let n = map.len();
+    let m = map[0].len();
     let start = State {
         pos: start,
-        dir: Vec3 { x: 1, y: 0 },
+        dir: START_DIR,
     };
 
-    let mut min_paths: HashMap<State, Vec<State>> = Default::default();
-    let mut min_scores: HashMap<State, i32> = Default::default();
-
-    min_paths.insert(start, Vec::new());
-    min_scores.insert(start, 0);
+    let mut scores = StateScores {
+        m,
+        min_scores: vec![u64::MAX; n * m * 4],
+        min_paths: vec![Vec::new(); n * m * 4],
+    };
+    let start_idx = scores.idx(start);
+    scores.min_scores[start_idx] = 0;
 
     let mut to_process = BinaryHeap::new();
     to_process.push(Reverse((0, start)));
 
-    while !to_process.is_empty() {
-        let (score, state) = to_process.pop().unwrap().0;
+    while let Some(Reverse((score, state))) = to_process.pop() {
+        if score > scores.score(state) {
+            continue;
+        }
 
-        let mut next_state = |next: State, cost: i32| {
+        let mut next_state = |next: State, cost: u32| {
             let x = next.pos.x as usize;
             let y = next.pos.y as usize;
-            let score = score + cost;
+            let score = score.checked_add(cost as u64).ok_or(MazeError::Overflow)?;
 
             if map[y][x] != Tile::Wall {
-                let min_score = min_scores.entry(next).or_insert(i32::MAX);
-                let min_path = min_paths.entry(next).or_insert_with(|| Vec::new());
+                let idx = scores.idx(next);
 
-                if score < *min_score {
-                    *min_score = score;
-                    min_path.clear();
+                if score < scores.min_scores[idx] {
+                    scores.min_scores[idx] = score;
+                    scores.min_paths[idx].clear();
                     to_process.push(Reverse((score, next)));
                 }
 
-                if score == *min_score {
-                    min_path.push(state);
+                if score == scores.min_scores[idx] {
+                    scores.min_paths[idx].push(state);
                 }
             }
+
+            Ok(())
         };
 
         let State { pos, dir } = state;
@@ -191,51 +189,197 @@ fn compute_dijkstras_with_state(map: &Vec<Vec<Tile>>, start: Vec3) -> HashMap<St
             },
         };
 
-        next_state(state_move, MOVE_COST);
-        next_state(state_left, TURN_COST);
-        next_state(state_right, TURN_COST);
+        next_state(state_move, costs.step)?;
+        next_state(state_left, costs.turn)?;
+        next_state(state_right, costs.turn)?;
     }
 
-    min_paths
+    Ok(scores)
 }
 
-fn part_one(map: &Vec<Vec<Tile>>) {
+/// The end can be reached facing any direction, so keep those that share the minimum score
+fn find_best_ends(scores: &StateScores, end: Vec3) -> Result<Vec<State>, MazeError> {
+    let ends: Vec<_> = DIRECTIONS
+        .iter()
+        .map(|&dir| State { pos: end, dir })
+        .filter(|&state| scores.score(state) != u64::MAX)
+        .collect();
+    let best = ends
+        .iter()
+        .map(|&state| scores.score(state))
+        .min()
+        .ok_or(MazeError::Unreachable)?;
+
+    Ok(ends
+        .into_iter()
+        .filter(|&state| scores.score(state) == best)
+        .collect())
+}
+
+fn find_best_tiles(map: &[Vec<Tile>], scores: &StateScores, ends: &[State]) -> BitGrid {
+    let mut tiles = BitGrid::new(map.len(), map[0].len());
+    let mut visited = BitGrid::with_layers(map.len(), map[0].len(), 4);
+    let mut min_path: Vec<State> = ends.to_vec();
+
+    while let Some(state) = min_path.pop() {
+        let (y, x) = (state.pos.y as usize, state.pos.x as usize);
+        if !visited.insert_at(y, x, state.layer()) {
+            continue;
+        }
+
+        tiles.insert(y, x);
+        min_path.extend_from_slice(scores.prev(state));
+    }
+
+    tiles
+}
+
+/// One of the best paths, from `start` to `end`. Free turns make states each other's previous
+/// states, so the walk back never revisits a state, and backs up when it runs out of new ones.
+fn find_best_path(map: &[Vec<Tile>], scores: &StateScores, start: State, end: State) -> Vec<State> {
+    let mut visited = BitGrid::with_layers(map.len(), map[0].len(), 4);
+    visited.insert_at(end.pos.y as usize, end.pos.x as usize, end.layer());
+    let mut path = vec![end];
+
+    while let Some(&state) = path.last() {
+        if state == start {
+            break;
+        }
+
+        let prev = scores
+            .prev(state)
+            .iter()
+            .find(|prev| visited.insert_at(prev.pos.y as usize, prev.pos.x as usize, prev.layer()));
+        match prev {
+            Some(&prev) => path.push(prev),
+            None => {
+                path.pop();
+            }
+        }
+    }
+
+    path.reverse();
+    path
+}
+
+fn part_one(map: &Vec<Vec<Tile>>, costs: Costs) {
     let (start, end) = find_start_and_end(map);
 
-    let min_scores = compute_dijkstras(map, start);
-    let min_score = min_scores[end.y as usize][end.x as usize];
+    let min_score = compute_dijkstras_with_state(map, start, costs)
+        .and_then(|scores| Ok(scores.score(find_best_ends(&scores, end)?[0])));
 
-    println!("{}", min_score);
+    match min_score {
+        Ok(min_score) => println!("{}", min_score),
+        Err(err) => eprintln!("error: {}", err),
+    }
 }
 
-fn part_two(map: &Vec<Vec<Tile>>) {
+fn part_two(map: &Vec<Vec<Tile>>, costs: Costs) {
     let (start, end) = find_start_and_end(map);
 
-    let min_paths = compute_dijkstras_with_state(map, start);
-    let min_paths_tiles = {
-        let end = State {
-            pos: end,
-            dir: Vec3 { x: 1, y: 0 },
-        };
+    let min_paths_tiles = compute_dijkstras_with_state(map, start, costs).and_then(|scores| {
+        let ends = find_best_ends(&scores, end)?;
+        Ok(find_best_tiles(map, &scores, &ends).count())
+    });
+
+    match min_paths_tiles {
+        Ok(min_paths_tiles) => println!("{}", min_paths_tiles),
+        Err(err) => eprintln!("error: {}", err),
+    }
+}
+
+/// The maze with one best path drawn as arrows, and the other best-path tiles as `O`
+fn render(map: &Vec<Vec<Tile>>, costs: Costs) -> Result<Vec<Vec<char>>, MazeError> {
+    let (start, end) = find_start_and_end(map);
 
-        let mut tiles = HashSet::new();
-        let mut min_path = Vec::new();
-        min_path.push(&end);
+    let scores = compute_dijkstras_with_state(map, start, costs)?;
+    let ends = find_best_ends(&scores, end)?;
+    let tiles = find_best_tiles(map, &scores, &ends);
+
+    let mut picture: Vec<Vec<char>> = map
+        .iter()
+        .enumerate()
+        .map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(x, tile)| match tile {
+                    Tile::Wall => '#',
+                    Tile::Start => 'S',
+                    Tile::End => 'E',
+                    Tile::Empty if tiles.contains(y, x) => 'O',
+                    Tile::Empty => '.',
+                })
+                .collect()
+        })
+        .collect();
 
-        while !min_path.is_empty() {
-            let state = min_path.pop().unwrap();
+    let start = State {
+        pos: start,
+        dir: START_DIR,
+    };
+    let path = find_best_path(map, &scores, start, ends[0]);
+    for states in path.windows(2) {
+        let (from, to) = (states[0], states[1]);
+        let (x, y) = (from.pos.x as usize, from.pos.y as usize);
+        if from.pos != to.pos && map[y][x] == Tile::Empty {
+            picture[y][x] = match (to.dir.x, to.dir.y) {
+                (1, 0) => '>',
+                (-1, 0) => '<',
+                (0, 1) => 'v',
+                (0, -1) => '^',
+                _ => unreachable!(),
+            };
+        }
+    }
 
-            tiles.insert(state.pos);
+    Ok(picture)
+}
 
-            for prev in min_paths.get(state).unwrap().iter() {
-                min_path.push(prev);
+/// Binary PPM of a rendered maze, with each tile drawn as a block of pixels
+fn to_ppm(picture: &[Vec<char>]) -> Vec<u8> {
+    const SCALE: usize = 5;
+    const ARROW: [&str; SCALE] = [
+        "..#..", // anti autoformat comment
+        "...#.", "#####", "...#.", "..#..",
+    ];
+
+    let height = picture.len();
+    let width = picture.first().map_or(0, Vec::len);
+    let mut ppm = format!("P6\n{} {}\n255\n", width * SCALE, height * SCALE).into_bytes();
+
+    for row in picture.iter() {
+        for py in 0..SCALE {
+            for &c in row.iter() {
+                for px in 0..SCALE {
+                    let (dx, dy) = match c {
+                        '>' => (1, 0),
+                        '<' => (-1, 0),
+                        'v' => (0, 1),
+                        '^' => (0, -1),
+                        _ => (0, 0),
+                    };
+
+                    // Rotate the pixel into the frame of a right-pointing arrow
+                    let (cx, cy) = (px as isize - 2, py as isize - 2);
+                    let along = (cx * dx + cy * dy + 2) as usize;
+                    let across = (cy * dx - cx * dy + 2) as usize;
+                    let on_arrow = (dx, dy) != (0, 0) && ARROW[across].as_bytes()[along] == b'#';
+
+                    let color = match c {
+                        '#' => [64, 64, 64],
+                        '.' => [16, 16, 16],
+                        'S' => [60, 200, 60],
+                        'E' => [200, 60, 60],
+                        _ if on_arrow => [160, 30, 30],
+                        _ => [230, 200, 60],
+                    };
+                    ppm.extend(color);
+                }
             }
         }
+    }
 
-        tiles.len()
-    };
-
-    println!("{}", min_paths_tiles);
+    ppm
 }
 
 fn main() -> std::io::Result<()> {
@@ -256,8 +400,34 @@ fn main() -> std::io::Result<()> {
         })
         .collect();
 
-    part_one(&map);
-    part_two(&map);
+    let costs = Costs {
+        step: aoc::option("move-cost")?.unwrap_or(COSTS.step),
+        turn: aoc::option("turn-cost")?.unwrap_or(COSTS.turn),
+    };
+
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("render") => {
+            let picture = match render(&map, costs) {
+                Ok(picture) => picture,
+                Err(err) => {
+                    eprintln!("error: {}", err);
+                    return Ok(());
+                }
+            };
+            if args.next().as_deref() == Some("ppm") {
+                std::io::stdout().write_all(&to_ppm(&picture))?;
+            } else {
+                for row in picture.iter() {
+                    println!("{}", row.iter().collect::<String>());
+                }
+            }
+        }
+        _ => {
+            part_one(&map, costs);
+            part_two(&map, costs);
+        }
+    }
 
     Ok(())
 }
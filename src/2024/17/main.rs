@@ -1,4 +1,7 @@
-use std::collections::VecDeque;
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+
+use thiserror::Error;
 
 #[derive(Debug, Default, Clone, Hash)]
 struct Machine {
@@ -10,51 +13,140 @@ struct Machine {
     output: Vec<u64>,
 }
 
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+enum MachineError {
+    #[error("reserved combo operand {operand} at ip {ip}")]
+    ReservedOperand { ip: usize, operand: u64 },
+    #[error("invalid opcode {opcode} at ip {ip}")]
+    InvalidOpcode { ip: usize, opcode: u64 },
+    #[error("opcode at ip {ip} has no operand")]
+    DanglingOperand { ip: usize },
+    #[error("program did not halt within {budget} steps")]
+    BudgetExhausted { budget: u64 },
+}
+
+/// Enough for any puzzle input by several orders of magnitude, override with `--budget`
+const STEP_BUDGET: u64 = 1_000_000;
+
 impl Machine {
-    fn run_once(&mut self) {
-        let opcode = self.program[self.ip];
-        let literal = self.program[self.ip + 1];
-        let combo = match self.program[self.ip + 1] {
-            0..=3 => literal,
-            4 => self.ra,
-            5 => self.rb,
-            6 => self.rc,
-            7 => 0,
-            _ => unreachable!(),
+    fn run_once(&mut self) -> Result<(), MachineError> {
+        let ip = self.ip;
+        let opcode = self.program[ip];
+        let literal = *self
+            .program
+            .get(ip + 1)
+            .ok_or(MachineError::DanglingOperand { ip })?;
+        let combo = || match literal {
+            0..=3 => Ok(literal),
+            4 => Ok(self.ra),
+            5 => Ok(self.rb),
+            6 => Ok(self.rc),
+            _ => Err(MachineError::ReservedOperand {
+                ip,
+                operand: literal,
+            }),
+        };
+
+        // Shifting by 64 or more bits leaves nothing behind
+        let shr = |value: u64, shift: u64| {
+            u32::try_from(shift)
+                .ok()
+                .and_then(|shift| value.checked_shr(shift))
+                .unwrap_or(0)
         };
 
         match opcode {
-            0 => self.ra = self.ra >> combo,  // adv
-            1 => self.rb = self.rb ^ literal, // bxl
-            2 => self.rb = combo % 8,         // bst
+            0 => self.ra = shr(self.ra, combo()?), // adv
+            1 => self.rb ^= literal,               // bxl
+            2 => self.rb = combo()? % 8,           // bst
             3 => {
                 // jnz
                 if self.ra != 0 {
                     self.ip = literal as usize;
+                    return Ok(());
                 }
             }
-            4 => self.rb = self.rb ^ self.rc, // bxc
-            5 => self.output.push(combo % 8), // out
-            6 => self.rb = self.ra >> combo,  // bdv
-            7 => self.rc = self.ra >> combo,  // cdv
-            _ => unreachable!(),
+            4 => self.rb ^= self.rc,               // bxc
+            5 => self.output.push(combo()? % 8),   // out
+            6 => self.rb = shr(self.ra, combo()?), // bdv
+            7 => self.rc = shr(self.ra, combo()?), // cdv
+            _ => return Err(MachineError::InvalidOpcode { ip, opcode }),
         }
 
-        if !(opcode == 3 && self.ra != 0) {
-            self.ip += 2;
-        }
+        self.ip += 2;
+        Ok(())
     }
 
-    fn run(&mut self) {
-        while self.ip + 1 < self.program.len() {
-            self.run_once();
+    fn run(&mut self, budget: u64) -> Result<(), MachineError> {
+        for _ in 0..budget {
+            if self.is_halted() {
+                return Ok(());
+            }
+            self.run_once()?;
+        }
+
+        if self.is_halted() {
+            Ok(())
+        } else {
+            Err(MachineError::BudgetExhausted { budget })
         }
     }
+
+    fn is_halted(&self) -> bool {
+        self.ip >= self.program.len()
+    }
+
+    fn disassemble_at(&self, ip: usize) -> String {
+        let opcode = self.program[ip];
+        let Some(&operand) = self.program.get(ip + 1) else {
+            return format!("{:<4}", opcode);
+        };
+
+        let combo = match operand {
+            0..=3 => operand.to_string(),
+            4 => "A".to_string(),
+            5 => "B".to_string(),
+            6 => "C".to_string(),
+            _ => "<reserved>".to_string(),
+        };
+
+        let (mnemonic, operand, effect) = match opcode {
+            0 => ("adv", combo.clone(), format!("A = A >> {}", combo)),
+            1 => ("bxl", operand.to_string(), format!("B = B ^ {}", operand)),
+            2 => ("bst", combo.clone(), format!("B = {} % 8", combo)),
+            3 => (
+                "jnz",
+                operand.to_string(),
+                format!("if A != 0 goto {}", operand),
+            ),
+            4 => ("bxc", operand.to_string(), "B = B ^ C".to_string()),
+            5 => ("out", combo.clone(), format!("out {} % 8", combo)),
+            6 => ("bdv", combo.clone(), format!("B = A >> {}", combo)),
+            7 => ("cdv", combo.clone(), format!("C = A >> {}", combo)),
+            _ => ("???", operand.to_string(), "invalid opcode".to_string()),
+        };
+
+        format!("{} {:<10} ; {}", mnemonic, operand, effect)
+    }
+
+    fn disassemble(&self) -> Vec<String> {
+        (0..self.program.len())
+            .step_by(2)
+            .map(|ip| format!("{:>3}: {}", ip, self.disassemble_at(ip)))
+            .collect()
+    }
+
+    fn registers(&self) -> String {
+        format!("A={} B={} C={}", self.ra, self.rb, self.rc)
+    }
 }
 
-fn part_one(machine: &Machine) {
+fn part_one(machine: &Machine, budget: u64) {
     let mut machine = machine.clone();
-    machine.run();
+    if let Err(err) = machine.run(budget) {
+        eprintln!("error: {}", err);
+        return;
+    }
 
     let output: Vec<_> = machine.output.iter().map(|num| num.to_string()).collect();
     let output = output.join(",");
@@ -62,58 +154,277 @@ fn part_one(machine: &Machine) {
     println!("{}", output);
 }
 
-fn part_two(machine: &Machine) {
-    // RANT: Big thanks to the rise of AI for making this year of AoC the worst it's ever been!
-
-    // I spent 2 days trying to find a generalized solution to this problem (assuming the input is
-    // always solvable), and such a solution is simply not possible (or would require hundreds of
-    // edge case checking / automated "manual" assembly analysis with many assumptions made on the
-    // input data, none of which are explained in detail by the problem statement).
-
-    // Although good for keeping AI in check, this problem feels too "hacky"
-
-    // I was looking forwards to returning to AoC, and finally pushing my solutions to VCS, but if
-    // this continues, this might be the last year I ever do AoC.
-
-    // My program:
-    // (1) Rb = Ra % 8
-    // (2) Rb = Rb ^ 2
-    // (3) Rc = Ra >> Rb
-    // (4) Rb = Rb ^ Rc
-    // (5) Ra = Ra >> 3
-    // (6) Rb = Rb ^ 7
-    // (7) OUT, Rb % 8
-    // (8) JNZ, 0
-
-    let mut possibilities = VecDeque::new();
-    possibilities.push_back((0, machine.program.len() - 1));
-
-    while !possibilities.is_empty() {
-        let (prev_ra, out_idx) = possibilities.pop_front().unwrap();
-
-        for offset in 0..8 {
-            let ra = (prev_ra << 3) + offset;
-            let mut rb: u64;
-            let rc: u64;
-
-            rb = ra % 8;
-            rb = rb ^ 2;
-            rc = ra >> rb;
-            rb = rb ^ rc;
-            //ra = ra >> 3;
-            rb = rb ^ 7;
-
-            let out = rb % 8;
-            if machine.program[out_idx] == out {
-                if out_idx == 0 {
-                    println!("{}", ra);
-                    return;
+#[derive(Debug, Error)]
+enum QuineError {
+    #[error("program has an odd number of values, so it can't be split into instructions")]
+    OddLength,
+    #[error("program must end with `jnz 0` and contain no other jumps")]
+    NotASingleLoop,
+    #[error("program must shift A with `adv 3` exactly once, and not write to A otherwise")]
+    NotShiftingByThree,
+    #[error("program must output exactly once per loop")]
+    NotOutputtingOnce,
+    #[error("no value of register A makes the program output itself")]
+    NoSolution,
+    #[error(transparent)]
+    Machine(#[from] MachineError),
+}
+
+/// Checks that the program is a single loop that outputs one value, then drops the lowest 3 bits
+/// of A, until A is zero. Then the i-th output only depends on bits 3i and up of the initial A.
+fn check_quine_shape(program: &[u64]) -> Result<(), QuineError> {
+    if !program.len().is_multiple_of(2) {
+        return Err(QuineError::OddLength);
+    }
+
+    let instructions: Vec<_> = program.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+
+    let jumps = instructions
+        .iter()
+        .filter(|(opcode, _)| *opcode == 3)
+        .count();
+    if jumps != 1 || instructions.last() != Some(&(3, 0)) {
+        return Err(QuineError::NotASingleLoop);
+    }
+
+    let shifts: Vec<_> = instructions
+        .iter()
+        .filter(|(opcode, _)| *opcode == 0)
+        .collect();
+    if shifts != [&(0, 3)] {
+        return Err(QuineError::NotShiftingByThree);
+    }
+
+    let outputs = instructions
+        .iter()
+        .filter(|(opcode, _)| *opcode == 5)
+        .count();
+    if outputs != 1 {
+        return Err(QuineError::NotOutputtingOnce);
+    }
+
+    Ok(())
+}
+
+fn find_quine(machine: &Machine, budget: u64) -> Result<u64, QuineError> {
+    check_quine_shape(&machine.program)?;
+    find_quine_from(machine, 0, machine.program.len(), budget)?.ok_or(QuineError::NoSolution)
+}
+
+/// Extends `prev_ra` by three bits at a time, from the last output backwards, running the real
+/// machine on every candidate. Smaller bits are tried first, so the first match is the minimum.
+fn find_quine_from(
+    machine: &Machine,
+    prev_ra: u64,
+    out_len: usize,
+    budget: u64,
+) -> Result<Option<u64>, MachineError> {
+    if out_len == 0 {
+        return Ok(Some(prev_ra));
+    }
+
+    if prev_ra.leading_zeros() < 3 {
+        return Ok(None);
+    }
+
+    let out_idx = out_len - 1;
+
+    for offset in 0..8 {
+        let ra = (prev_ra << 3) | offset;
+        let mut trial = Machine {
+            ra,
+            rb: machine.rb,
+            rc: machine.rc,
+            program: machine.program.clone(),
+            ..Default::default()
+        };
+        trial.run(budget)?;
+
+        if trial.output == machine.program[out_idx..] {
+            if let Some(ra) = find_quine_from(machine, ra, out_idx, budget)? {
+                return Ok(Some(ra));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn part_two(machine: &Machine, budget: u64) {
+    match find_quine(machine, budget) {
+        Ok(ra) => println!("{}", ra),
+        Err(err) => eprintln!("error: {}", err),
+    }
+}
+
+fn trace(machine: &Machine, budget: u64) {
+    let mut machine = machine.clone();
+    let mut step = 0;
+
+    // Jumps can land on odd addresses, so size the column for every address
+    let width = (0..machine.program.len())
+        .map(|ip| machine.disassemble_at(ip).len())
+        .max()
+        .unwrap_or(0);
+
+    println!(
+        "{:>6}  {:<width$}  {}",
+        step,
+        "start",
+        machine.registers(),
+        width = width + 5
+    );
+    while !machine.is_halted() {
+        if step == budget {
+            println!("error: {}", MachineError::BudgetExhausted { budget: step });
+            return;
+        }
+
+        let ip = machine.ip;
+        if let Err(err) = machine.run_once() {
+            println!("error: {}", err);
+            return;
+        }
+        step += 1;
+
+        println!(
+            "{:>6}  {:>3}: {:<width$}  {}",
+            step,
+            ip,
+            machine.disassemble_at(ip),
+            machine.registers()
+        );
+    }
+
+    let output: Vec<_> = machine.output.iter().map(|num| num.to_string()).collect();
+    println!("output: {}", output.join(","));
+}
+
+fn debug(initial: &Machine, budget: u64) -> std::io::Result<()> {
+    const HELP: &str = "\
+commands:
+  s, step [n]        run n instructions (default 1)
+  c, continue        run until a breakpoint or halt
+  b, break <ip>      toggle a breakpoint
+  set <a|b|c> <n>    set a register
+  r, regs            show registers and output
+  d, disasm          show the program
+  reset              restart from the initial state
+  h, help            show this message
+  q, quit            exit";
+
+    let mut machine = initial.clone();
+    let mut breakpoints = BTreeSet::new();
+
+    let show = |machine: &Machine| {
+        let output: Vec<_> = machine.output.iter().map(|num| num.to_string()).collect();
+        println!("{}  out=[{}]", machine.registers(), output.join(","));
+        if machine.is_halted() {
+            println!("halted");
+        } else {
+            println!("{:>3}: {}", machine.ip, machine.disassemble_at(machine.ip));
+        }
+    };
+
+    println!("{}", HELP);
+    show(&machine);
+
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        print!("> ");
+        std::io::stdout().flush()?;
+
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line?;
+        let args: Vec<_> = line.split_whitespace().collect();
+
+        match args[..] {
+            [] => continue,
+            ["s" | "step"] | ["s" | "step", _] => {
+                let Ok(steps) = args.get(1).unwrap_or(&"1").parse::<u64>() else {
+                    println!("invalid step count");
+                    continue;
+                };
+                for _ in 0..steps {
+                    if machine.is_halted() {
+                        break;
+                    }
+                    if let Err(err) = machine.run_once() {
+                        println!("error: {}", err);
+                        break;
+                    }
                 }
+                show(&machine);
+            }
+            ["c" | "continue"] => {
+                let mut steps = 0;
+                while !machine.is_halted() {
+                    if steps == budget {
+                        println!("error: {}", MachineError::BudgetExhausted { budget: steps });
+                        break;
+                    }
+                    if let Err(err) = machine.run_once() {
+                        println!("error: {}", err);
+                        break;
+                    }
+                    steps += 1;
 
-                possibilities.push_back((ra, out_idx - 1));
+                    // Checked after stepping, so that continuing from a breakpoint makes progress
+                    if breakpoints.contains(&machine.ip) {
+                        break;
+                    }
+                }
+                show(&machine);
             }
+            ["b" | "break", ip] => match ip.parse::<usize>() {
+                Ok(ip) if breakpoints.remove(&ip) => println!("removed breakpoint at {}", ip),
+                Ok(ip) => {
+                    breakpoints.insert(ip);
+                    println!("added breakpoint at {}", ip);
+                }
+                Err(_) => println!("invalid instruction pointer"),
+            },
+            ["set", register, value] => {
+                let Ok(value) = value.parse() else {
+                    println!("invalid value");
+                    continue;
+                };
+                match register {
+                    "a" | "A" => machine.ra = value,
+                    "b" | "B" => machine.rb = value,
+                    "c" | "C" => machine.rc = value,
+                    _ => {
+                        println!("unknown register");
+                        continue;
+                    }
+                }
+                show(&machine);
+            }
+            ["r" | "regs"] => show(&machine),
+            ["d" | "disasm"] => {
+                for (idx, line) in machine.disassemble().iter().enumerate() {
+                    let marker = if idx * 2 == machine.ip { ">" } else { " " };
+                    let breakpoint = if breakpoints.contains(&(idx * 2)) {
+                        "*"
+                    } else {
+                        " "
+                    };
+                    println!("{}{}{}", marker, breakpoint, line);
+                }
+            }
+            ["reset"] => {
+                machine = initial.clone();
+                show(&machine);
+            }
+            ["h" | "help"] => println!("{}", HELP),
+            ["q" | "quit"] => break,
+            _ => println!("unknown command, try `help`"),
         }
     }
+
+    Ok(())
 }
 
 fn main() -> std::io::Result<()> {
@@ -141,8 +452,21 @@ fn main() -> std::io::Result<()> {
         ..Default::default()
     };
 
-    part_one(&machine);
-    part_two(&machine);
+    let budget = aoc::option("budget")?.unwrap_or(STEP_BUDGET);
+
+    match std::env::args().nth(1).as_deref() {
+        Some("disasm") => {
+            for line in machine.disassemble() {
+                println!("{}", line);
+            }
+        }
+        Some("trace") => trace(&machine, budget),
+        Some("debug") => debug(&machine, budget)?,
+        _ => {
+            part_one(&machine, budget);
+            part_two(&machine, budget);
+        }
+    }
 
     Ok(())
 }
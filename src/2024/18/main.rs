@@ -1,3 +1,4 @@
+use aoc::bitset::BitGrid;
 use std::collections::{HashSet, VecDeque};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -6,21 +7,56 @@ struct Coord {
     y: isize,
 }
 
-const WIDTH: isize = 70;
-const HEIGHT: isize = 70;
+/// Coordinates range over 0..=width and 0..=height
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Memory {
+    width: isize,
+    height: isize,
+    bytes: usize,
+}
+
+const MEMORY: Memory = Memory {
+    width: 70,
+    height: 70,
+    bytes: 1024,
+};
+const EXAMPLE_MEMORY: Memory = Memory {
+    width: 6,
+    height: 6,
+    bytes: 12,
+};
+
+/// The memory size isn't part of the input, but the example is the only input that fits in 6x6
+fn detect_memory(incoming: &[Coord]) -> Memory {
+    let fits_example = incoming.iter().all(|coord| {
+        (0..=EXAMPLE_MEMORY.width).contains(&coord.x)
+            && (0..=EXAMPLE_MEMORY.height).contains(&coord.y)
+    });
+
+    if fits_example {
+        EXAMPLE_MEMORY
+    } else {
+        MEMORY
+    }
+}
 
-fn part_one(incoming: &Vec<Coord>) {
-    let corrupted: HashSet<Coord> = incoming.iter().cloned().take(1024).collect();
+fn part_one(incoming: &Vec<Coord>, memory: Memory) {
+    let Memory {
+        width,
+        height,
+        bytes,
+    } = memory;
+    let corrupted: HashSet<Coord> = incoming.iter().cloned().take(bytes).collect();
 
     let start = Coord { x: 0, y: 0 };
     let end = Coord {
-        x: WIDTH,
-        y: HEIGHT,
+        x: width,
+        y: height,
     };
 
     // Simple BFS due to all steps having the same weight
-    let mut visited = HashSet::new();
-    visited.insert(start);
+    let mut visited = BitGrid::new((height + 1) as usize, (width + 1) as usize);
+    visited.insert(start.y as usize, start.x as usize);
     let mut to_visit = VecDeque::new();
     to_visit.push_back((start, 0));
 
@@ -44,10 +80,10 @@ fn part_one(incoming: &Vec<Coord>) {
                 y: coord.y + direction.y,
             };
 
-            if (0..=WIDTH).contains(&next_coord.x)
-                && (0..=HEIGHT).contains(&next_coord.y)
+            if (0..=width).contains(&next_coord.x)
+                && (0..=height).contains(&next_coord.y)
                 && !corrupted.contains(&next_coord)
-                && visited.insert(next_coord)
+                && visited.insert(next_coord.y as usize, next_coord.x as usize)
             {
                 to_visit.push_back((next_coord, steps + 1));
             }
@@ -55,50 +91,69 @@ fn part_one(incoming: &Vec<Coord>) {
     }
 }
 
-fn part_two(incoming: &Vec<Coord>) {
-    let mut corrupted = HashSet::new();
+fn part_two(incoming: &Vec<Coord>, memory: Memory, show_index: bool) {
+    let Memory { width, height, .. } = memory;
+    let cols = (width + 1) as usize;
+    let rows = (height + 1) as usize;
+    let index = |coord: Coord| coord.y as usize * cols + coord.x as usize;
+
+    // Time at which each cell first becomes corrupted
+    let mut corrupted_at = vec![None; rows * cols];
+    for (time, coord) in incoming.iter().enumerate() {
+        corrupted_at[index(*coord)].get_or_insert(time);
+    }
 
     let start = Coord { x: 0, y: 0 };
     let end = Coord {
-        x: WIDTH,
-        y: HEIGHT,
+        x: width,
+        y: height,
     };
 
-    for to_corrupt in incoming.iter().cloned() {
-        corrupted.insert(to_corrupt);
-
-        let mut visited = HashSet::new();
-        visited.insert(start);
-        let mut to_visit = VecDeque::new();
-        to_visit.push_back(start);
-
-        while !to_visit.is_empty() {
-            let coord = to_visit.pop_front().unwrap();
-
-            let directions = &[
-                Coord { x: 1, y: 0 },
-                Coord { x: -1, y: 0 },
-                Coord { x: 0, y: 1 },
-                Coord { x: 0, y: -1 },
-            ][..];
-            for direction in directions {
-                let next_coord = Coord {
-                    x: coord.x + direction.x,
-                    y: coord.y + direction.y,
-                };
-
-                if (0..=WIDTH).contains(&next_coord.x)
-                    && (0..=HEIGHT).contains(&next_coord.y)
-                    && !corrupted.contains(&next_coord)
-                    && visited.insert(next_coord)
-                {
-                    to_visit.push_back(next_coord);
-                }
+    // Run time backwards: start with every byte fallen, then remove them one by one
+    // and join the freed cell with its free neighbours until start and end connect.
+    let mut free = corrupted_at.iter().map(Option::is_none).collect::<Vec<_>>();
+    let mut regions = aoc::dsu::DisjointSet::from_grid(
+        &free.chunks(cols).map(<[bool]>::to_vec).collect::<Vec<_>>(),
+        |a, b| *a && *b,
+    );
+
+    if regions.same(index(start), index(end)) {
+        eprintln!("error: the exit is still reachable after every byte has fallen");
+        return;
+    }
+
+    for (time, to_free) in incoming.iter().cloned().enumerate().rev() {
+        if corrupted_at[index(to_free)] != Some(time) {
+            continue;
+        }
+
+        free[index(to_free)] = true;
+
+        let directions = &[
+            Coord { x: 1, y: 0 },
+            Coord { x: -1, y: 0 },
+            Coord { x: 0, y: 1 },
+            Coord { x: 0, y: -1 },
+        ][..];
+        for direction in directions {
+            let next_coord = Coord {
+                x: to_free.x + direction.x,
+                y: to_free.y + direction.y,
+            };
+
+            if (0..=width).contains(&next_coord.x)
+                && (0..=height).contains(&next_coord.y)
+                && free[index(next_coord)]
+            {
+                regions.union(index(to_free), index(next_coord));
             }
         }
 
-        if !visited.contains(&end) {
-            println!("{},{}", to_corrupt.x, to_corrupt.y);
+        if regions.same(index(start), index(end)) {
+            println!("{},{}", to_free.x, to_free.y);
+            if show_index {
+                println!("index {}", time);
+            }
             break;
         }
     }
@@ -117,8 +172,26 @@ fn main() -> std::io::Result<()> {
         })
         .collect();
 
-    part_one(&incoming);
-    part_two(&incoming);
+    let detected = detect_memory(&incoming);
+    let memory = Memory {
+        width: aoc::option("width")?.unwrap_or(detected.width),
+        height: aoc::option("height")?.unwrap_or(detected.height),
+        bytes: aoc::option("bytes")?.unwrap_or(detected.bytes),
+    };
+
+    if let Some(coord) = incoming.iter().find(|coord| {
+        !(0..=memory.width).contains(&coord.x) || !(0..=memory.height).contains(&coord.y)
+    }) {
+        eprintln!(
+            "error: byte {},{} falls outside of memory",
+            coord.x, coord.y
+        );
+        return Ok(());
+    }
+
+    part_one(&incoming, memory);
+    let show_index = std::env::args().any(|arg| arg == "--index");
+    part_two(&incoming, memory, show_index);
 
     Ok(())
 }
@@ -1,40 +1,191 @@
-fn count_pattern_arrangements(design: &String, patterns: &Vec<String>) -> usize {
+use std::collections::HashMap;
+
+/// Prefix tree of the towel patterns
+#[derive(Debug, Default, Clone)]
+struct Trie {
+    children: Vec<HashMap<u8, usize>>,
+    is_pattern: Vec<bool>,
+}
+
+impl Trie {
+    fn new(patterns: &[String]) -> Self {
+        let mut trie = Self {
+            children: vec![HashMap::new()],
+            is_pattern: vec![false],
+        };
+
+        for pattern in patterns.iter() {
+            let mut node = 0;
+            for byte in pattern.bytes() {
+                node = match trie.children[node].get(&byte) {
+                    Some(&child) => child,
+                    None => {
+                        let child = trie.children.len();
+                        trie.children.push(HashMap::new());
+                        trie.is_pattern.push(false);
+                        trie.children[node].insert(byte, child);
+                        child
+                    }
+                };
+            }
+            trie.is_pattern[node] = true;
+        }
+
+        trie
+    }
+
+    /// Lengths of the patterns that `design` starts with, shortest first
+    fn prefix_lengths<'a>(&'a self, design: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        let mut node = 0;
+        design
+            .iter()
+            .map_while(move |byte| {
+                node = *self.children[node].get(byte)?;
+                Some(node)
+            })
+            .enumerate()
+            .filter(|(_, node)| self.is_pattern[*node])
+            .map(|(idx, _)| idx + 1)
+    }
+}
+
+/// None if the count doesn't fit in a u128
+fn count_pattern_arrangements(design: &str, trie: &Trie) -> Option<u128> {
+    let design = design.as_bytes();
     let n = design.len();
-    let mut dp = vec![0; n + 1];
+    let mut dp = vec![0u128; n + 1];
 
     dp[0] = 1;
     for i in 0..n {
         if dp[i] == 0 {
             continue;
         }
-        let design = &design[i..];
-        for pattern in patterns.iter() {
-            if design.starts_with(pattern) {
-                let k = pattern.len();
-                if i + k <= n {
-                    dp[i + k] += dp[i];
-                }
+        for k in trie.prefix_lengths(&design[i..]) {
+            dp[i + k] = dp[i + k].checked_add(dp[i])?;
+        }
+    }
+
+    Some(dp[n])
+}
+
+/// Up to `limit` arrangements, as the patterns used in order
+fn list_pattern_arrangements<'a>(design: &'a str, trie: &Trie, limit: usize) -> Vec<Vec<&'a str>> {
+    let bytes = design.as_bytes();
+    let n = bytes.len();
+
+    // Only step onto positions from which the rest of the design can still be formed
+    let mut can_finish = vec![false; n + 1];
+    can_finish[n] = true;
+    for i in (0..n).rev() {
+        can_finish[i] = trie.prefix_lengths(&bytes[i..]).any(|k| can_finish[i + k]);
+    }
+
+    fn search<'a>(
+        design: &'a str,
+        i: usize,
+        trie: &Trie,
+        can_finish: &[bool],
+        limit: usize,
+        used: &mut Vec<&'a str>,
+        arrangements: &mut Vec<Vec<&'a str>>,
+    ) {
+        if i == design.len() {
+            arrangements.push(used.clone());
+            return;
+        }
+
+        for k in trie.prefix_lengths(&design.as_bytes()[i..]) {
+            if arrangements.len() >= limit {
+                return;
             }
+            if can_finish[i + k] {
+                used.push(&design[i..i + k]);
+                search(design, i + k, trie, can_finish, limit, used, arrangements);
+                used.pop();
+            }
+        }
+    }
+
+    let mut arrangements = Vec::new();
+    if limit > 0 && can_finish[0] {
+        search(
+            design,
+            0,
+            trie,
+            &can_finish,
+            limit,
+            &mut Vec::new(),
+            &mut arrangements,
+        );
+    }
+
+    arrangements
+}
+
+/// Length of the longest start of the design that the patterns can form
+fn longest_formable_prefix(design: &str, trie: &Trie) -> usize {
+    let design = design.as_bytes();
+    let n = design.len();
+    let mut reachable = vec![false; n + 1];
+
+    reachable[0] = true;
+    let mut longest = 0;
+    for i in 0..=n {
+        if !reachable[i] {
+            continue;
+        }
+        longest = i;
+        for k in trie.prefix_lengths(&design[i..]) {
+            reachable[i + k] = true;
         }
     }
 
-    dp[n]
+    longest
 }
 
-fn part_one(patterns: &Vec<String>, designs: &Vec<String>) {
+fn part_one(trie: &Trie, designs: &Vec<String>) {
     let count = designs
         .iter()
-        .filter(|design| count_pattern_arrangements(design, patterns) > 0)
+        .filter(|design| count_pattern_arrangements(design, trie) != Some(0))
         .count();
     println!("{}", count);
 }
 
-fn part_two(patterns: &Vec<String>, designs: &Vec<String>) {
-    let sum: usize = designs
-        .iter()
-        .map(|design| count_pattern_arrangements(design, patterns))
-        .sum();
-    println!("{}", sum);
+fn part_two(trie: &Trie, designs: &Vec<String>) {
+    let sum = designs.iter().try_fold(0u128, |sum, design| {
+        sum.checked_add(count_pattern_arrangements(design, trie)?)
+    });
+
+    match sum {
+        Some(sum) => println!("{}", sum),
+        None => eprintln!("error: the number of arrangements doesn't fit in a u128"),
+    }
+}
+
+/// Prints up to `limit` arrangements of every design, or how far an impossible one gets
+fn explain(trie: &Trie, designs: &[String], limit: usize) {
+    for design in designs.iter() {
+        let count = match count_pattern_arrangements(design, trie) {
+            Some(0) => {
+                let longest = longest_formable_prefix(design, trie);
+                println!(
+                    "{}: impossible, longest formable prefix is `{}` ({} of {})",
+                    design,
+                    &design[..longest],
+                    longest,
+                    design.len()
+                );
+                continue;
+            }
+            Some(count) => count.to_string(),
+            None => String::from("more than u128::MAX"),
+        };
+
+        println!("{}: {} arrangements", design, count);
+        for arrangement in list_pattern_arrangements(design, trie, limit) {
+            println!("    {}", arrangement.join(", "));
+        }
+    }
 }
 
 fn main() -> std::io::Result<()> {
@@ -44,8 +195,15 @@ fn main() -> std::io::Result<()> {
     let patterns: Vec<String> = patterns.split(", ").map(str::to_string).collect();
     let designs: Vec<String> = designs.lines().map(str::to_string).collect();
 
-    part_one(&patterns, &designs);
-    part_two(&patterns, &designs);
+    let trie = Trie::new(&patterns);
+
+    match std::env::args().nth(1).as_deref() {
+        Some("explain") => explain(&trie, &designs, aoc::option("limit")?.unwrap_or(3)),
+        _ => {
+            part_one(&trie, &designs);
+            part_two(&trie, &designs);
+        }
+    }
 
     Ok(())
 }
@@ -1,6 +1,6 @@
 use std::{
     cmp::Reverse,
-    collections::{BinaryHeap, HashMap},
+    collections::{BTreeMap, BinaryHeap},
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -41,7 +41,8 @@ fn find_start_and_end(map: &Vec<Vec<Tile>>) -> (Coord, Coord) {
     (start, end)
 }
 
-fn compute_reverse_dijkstras(map: &Vec<Vec<Tile>>) -> HashMap<Coord, i64> {
+/// Moves until the end for every tile, None for walls and tiles that can't reach it
+fn compute_reverse_dijkstras(map: &Vec<Vec<Tile>>) -> Vec<Vec<Option<i64>>> {
     // Intuition: Reverse Dijkstra's Algorithm
     // - Since there's only a single path from S>E without cheating, the paths match after cheating
     // - For each tile, mark the number of moves until E is reached
@@ -51,8 +52,8 @@ fn compute_reverse_dijkstras(map: &Vec<Vec<Tile>>) -> HashMap<Coord, i64> {
     let m = map[0].len();
     let (_start, end) = find_start_and_end(map);
 
-    let mut moves_until = HashMap::new();
-    moves_until.insert(end, 0);
+    let mut moves_until = vec![vec![None; m]; n];
+    moves_until[end.row as usize][end.col as usize] = Some(0);
 
     let mut to_process = BinaryHeap::new();
     to_process.push(Reverse((0, end)));
@@ -77,9 +78,9 @@ fn compute_reverse_dijkstras(map: &Vec<Vec<Tile>>) -> HashMap<Coord, i64> {
                 && (0..m as isize).contains(&coord.col)
                 && map[coord.row as usize][coord.col as usize] != Tile::Wall
             {
-                let next_moves = moves_until.entry(coord).or_insert(i64::MAX);
-                if moves < *next_moves {
-                    *next_moves = moves;
+                let next_moves = &mut moves_until[coord.row as usize][coord.col as usize];
+                if next_moves.is_none_or(|next_moves| moves < next_moves) {
+                    *next_moves = Some(moves);
 
                     to_process.push(Reverse((moves, coord)));
                 }
@@ -90,63 +91,76 @@ fn compute_reverse_dijkstras(map: &Vec<Vec<Tile>>) -> HashMap<Coord, i64> {
     moves_until
 }
 
-fn part_one(map: &Vec<Vec<Tile>>) {
-    let moves_until = compute_reverse_dijkstras(map);
-
-    let mut count = 0;
-    for (coord, moves) in moves_until.iter() {
-        let dirs = &[
-            Coord { row: 2, col: 0 },
-            Coord { row: -2, col: 0 },
-            Coord { row: 0, col: 2 },
-            Coord { row: 0, col: -2 },
-            Coord { row: 1, col: 1 },
-            Coord { row: 1, col: -1 },
-            Coord { row: -1, col: 1 },
-            Coord { row: -1, col: -1 },
-        ][..];
-        for dir in dirs {
-            let next_coord = Coord {
-                row: coord.row + dir.row,
-                col: coord.col + dir.col,
+/// Number of cheats for every amount of moves saved, for cheats of up to `radius` moves that
+/// save at least `min_saving` moves
+fn count_cheats(
+    moves_until: &[Vec<Option<i64>>],
+    radius: i64,
+    min_saving: i64,
+) -> BTreeMap<i64, usize> {
+    let mut savings = BTreeMap::new();
+
+    let get = |row: isize, col: isize| -> Option<i64> {
+        let row = usize::try_from(row).ok()?;
+        let col = usize::try_from(col).ok()?;
+        *moves_until.get(row)?.get(col)?
+    };
+
+    for (row, tiles) in moves_until.iter().enumerate() {
+        for (col, moves) in tiles.iter().enumerate() {
+            let Some(moves) = moves else {
+                continue;
             };
 
-            if let Some(next_moves) = moves_until.get(&next_coord) {
-                let moves_saved = moves - (next_moves + 2);
-                if moves_saved >= 100 {
-                    count += 1;
+            for delta_row in -radius..=radius {
+                let max_delta_col = radius - delta_row.abs();
+                for delta_col in -max_delta_col..=max_delta_col {
+                    let next_row = row as isize + delta_row as isize;
+                    let next_col = col as isize + delta_col as isize;
+
+                    if let Some(next_moves) = get(next_row, next_col) {
+                        let moves_inbetween = delta_row.abs() + delta_col.abs();
+                        let moves_saved = moves - (next_moves + moves_inbetween);
+                        if moves_saved >= min_saving {
+                            *savings.entry(moves_saved).or_insert(0) += 1;
+                        }
+                    }
                 }
             }
         }
     }
 
+    savings
+}
+
+fn part_one(map: &Vec<Vec<Tile>>) {
+    let moves_until = compute_reverse_dijkstras(map);
+    let count: usize = count_cheats(&moves_until, 2, 100).values().sum();
+
     println!("{}", count);
 }
 
 fn part_two(map: &Vec<Vec<Tile>>) {
-    const CHEAT_MAX: i64 = 20;
-
     let moves_until = compute_reverse_dijkstras(map);
+    let count: usize = count_cheats(&moves_until, 20, 100).values().sum();
 
-    let mut count = 0;
-    for (coord, moves) in moves_until.iter() {
-        for (next_coord, next_moves) in moves_until.iter() {
-            let moves_inbetween = {
-                let delta_rows = (next_coord.row - coord.row).abs() as i64;
-                let delta_cols = (next_coord.col - coord.col).abs() as i64;
-                delta_rows + delta_cols
-            };
+    println!("{}", count);
+}
 
-            if moves_inbetween <= CHEAT_MAX {
-                let moves_saved = moves - (next_moves + moves_inbetween);
-                if moves_saved >= 100 {
-                    count += 1;
-                }
-            }
+/// Lists the cheats the same way the puzzle examples do
+fn print_histogram(map: &Vec<Vec<Tile>>, radius: i64, min_saving: i64) {
+    let moves_until = compute_reverse_dijkstras(map);
+
+    for (moves_saved, count) in count_cheats(&moves_until, radius, min_saving) {
+        if count == 1 {
+            println!("There is one cheat that saves {} picoseconds.", moves_saved);
+        } else {
+            println!(
+                "There are {} cheats that save {} picoseconds.",
+                count, moves_saved
+            );
         }
     }
-
-    println!("{}", count);
 }
 
 fn main() -> std::io::Result<()> {
@@ -167,8 +181,17 @@ fn main() -> std::io::Result<()> {
         })
         .collect();
 
-    part_one(&map);
-    part_two(&map);
+    match std::env::args().nth(1).as_deref() {
+        Some("histogram") => print_histogram(
+            &map,
+            aoc::option("radius")?.unwrap_or(2),
+            aoc::option("min-saving")?.unwrap_or(1),
+        ),
+        _ => {
+            part_one(&map);
+            part_two(&map);
+        }
+    }
 
     Ok(())
 }
@@ -3,266 +3,323 @@ use std::{
     collections::{BinaryHeap, HashMap},
 };
 
-fn key_to_num(key: char) -> usize {
-    match key {
-        '^' => 0,
-        '<' => 1,
-        'v' => 2,
-        '>' => 3,
-        'A' => 4,
-        _ => unreachable!(),
-    }
+use thiserror::Error;
+
+const NUMERIC_KEYPAD: &str = "789\n456\n123\n 0A";
+const DIRECTIONAL_KEYPAD: &str = " ^A\n<v>";
+
+const DIRECTIONS: [char; 4] = ['^', '<', 'v', '>'];
+
+/// Longest human sequence the `sequence` subcommand expands, override with `--max-length`
+const MAX_SEQUENCE_LENGTH: u128 = 1_000_000;
+
+/// Any rectangular layout of keys, where ' ' is a gap that robot arms must never point at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Keypad {
+    rows: Vec<Vec<char>>,
 }
 
-fn key_from_num(num: usize) -> char {
-    match num {
-        0 => '^',
-        1 => '<',
-        2 => 'v',
-        3 => '>',
-        4 => 'A',
-        _ => unreachable!(),
+impl Keypad {
+    fn parse(layout: &str) -> Self {
+        let rows = layout.lines().map(|line| line.chars().collect()).collect();
+        Self { rows }
+    }
+
+    fn position(&self, key: char) -> Option<(isize, isize)> {
+        self.rows.iter().enumerate().find_map(|(row, keys)| {
+            let col = keys.iter().position(|k| *k == key && key != ' ')?;
+            Some((row as isize, col as isize))
+        })
+    }
+
+    fn key_at(&self, (row, col): (isize, isize)) -> Option<char> {
+        let key = *self
+            .rows
+            .get(usize::try_from(row).ok()?)?
+            .get(usize::try_from(col).ok()?)?;
+        (key != ' ').then_some(key)
     }
 }
 
-#[deprecated]
-fn key_to_coord(key: char) -> (isize, isize) {
+fn direction_of(key: char) -> (isize, isize) {
     match key {
-        '^' => (0, 1),
-        '<' => (1, 0),
-        'v' => (1, 1),
-        '>' => (1, 2),
-        'A' => (0, 2),
+        '^' => (-1, 0),
+        '<' => (0, -1),
+        'v' => (1, 0),
+        '>' => (0, 1),
         _ => unreachable!(),
     }
 }
 
-fn compute_keypress_costs(robots: usize) -> Vec<Vec<Vec<i64>>> {
-    // Intuition: Dynamic Programming, with Dijkstra's baked into each calculation step
-
-    // Generalization:
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+enum ChainError {
+    #[error("key {key:?} doesn't exist on keypad {layer}")]
+    MissingKey { layer: usize, key: char },
+    #[error("no path from {from:?} to {to:?} on keypad {layer}")]
+    Unreachable { layer: usize, from: char, to: char },
+    #[error("key press count overflows u128 at keypad {layer}")]
+    Overflow { layer: usize },
+    #[error("robot arm at keypad {layer} points at a gap")]
+    Panicked { layer: usize },
+    #[error("sequence would be {length} keys long, more than the limit of {limit}")]
+    TooLong { length: u128, limit: u128 },
+}
 
-    // Press(N, X, Y) = Cost of moving Robot N from key X to Y and pressing Y
+/// Keypads from the door (layer 0) to the one the human types on (the last layer). Every layer
+/// after the first is pressed by a robot whose arm is steered by the next layer, so those keypads
+/// must have the directional keys and `A`.
+struct KeypadChain {
+    layers: Vec<Keypad>,
+    // (layer, from, to) => (human presses, keys to press on the next layer)
+    moves: HashMap<(usize, char, char), (u128, String)>,
+}
 
-    // Press(0, X, Y) = 1 (Human has 0 cost moves)
+impl KeypadChain {
+    fn new(layers: Vec<Keypad>) -> Result<Self, ChainError> {
+        for (layer, keypad) in layers.iter().enumerate().skip(1) {
+            for key in DIRECTIONS.into_iter().chain(['A']) {
+                if keypad.position(key).is_none() {
+                    return Err(ChainError::MissingKey { layer, key });
+                }
+            }
+        }
 
-    // Press(N, X, Y) = Find directional path I-J-K for previous robot minimizing:
-    //     Press(N-1, A, I) + Press(N-1, I, J) + Press(N-1, J, K) + Press(N-1, K, A)
+        Ok(Self {
+            layers,
+            moves: HashMap::new(),
+        })
+    }
 
-    let dirpad = [
-        [' ', '^', 'A'], // anti autoformat comment
-        ['<', 'v', '>'],
-    ];
-    let n = dirpad.len();
-    let m = dirpad[0].len();
+    /// Numeric keypad on the door, operated through `robots` directional keypads used by robots,
+    /// and one more directional keypad for the human.
+    fn door(robots: usize) -> Self {
+        let mut layers = vec![Keypad::parse(NUMERIC_KEYPAD)];
+        layers.extend(std::iter::repeat_n(
+            Keypad::parse(DIRECTIONAL_KEYPAD),
+            robots + 1,
+        ));
+        Self::new(layers).expect("built-in keypads are valid")
+    }
 
-    let mut dp = vec![vec![vec![0; 5]; 5]; robots + 1];
+    fn human_layer(&self) -> usize {
+        self.layers.len() - 1
+    }
 
-    for key1 in 0..5 {
-        for key2 in 0..5 {
-            dp[0][key1][key2] = 1;
+    /// Human key presses needed to move the arm over `layer` from `from` to `to` and press `to`,
+    /// assuming every arm above it starts and ends on `A`.
+    fn cost(&mut self, layer: usize, from: char, to: char) -> Result<u128, ChainError> {
+        if layer == self.human_layer() {
+            return Ok(1);
         }
+
+        Ok(self.best_move(layer, from, to)?.0)
     }
 
-    for robot in 1..=robots {
-        for key1 in 0..5 {
-            for key2 in 0..5 {
-                let (key1_coord, key2_coord) = {
-                    let (key1_char, key2_char) = (key_from_num(key1), key_from_num(key2));
-                    let mut key1_coord = (0, 0);
-                    let mut key2_coord = (0, 0);
-                    for (row, keys) in dirpad.iter().enumerate() {
-                        for (col, key) in keys.iter().enumerate() {
-                            let coord = (row as isize, col as isize);
-                            if *key == key1_char {
-                                key1_coord = coord;
-                            }
-                            if *key == key2_char {
-                                key2_coord = coord;
-                            }
-                        }
-                    }
-                    (key1_coord, key2_coord)
-                };
-
-                let mut min_costs = HashMap::new();
-                min_costs.insert((key1_coord, 'A'), 0);
-
-                let mut dijkstra = BinaryHeap::new();
-                dijkstra.push(Reverse((0, (key1_coord, 'A'))));
-
-                while !dijkstra.is_empty() {
-                    let (cost, state) = dijkstra.pop().unwrap().0;
-                    let (coord, prev_key_char) = state;
-                    let prev_key = key_to_num(prev_key_char);
-
-                    for dir_key_char in ['^', '<', 'v', '>'] {
-                        let dir_key = key_to_num(dir_key_char);
-                        let dir = match dir_key_char {
-                            '^' => (-1, 0),
-                            '<' => (0, -1),
-                            'v' => (1, 0),
-                            '>' => (0, 1),
-                            _ => unreachable!(),
-                        };
-
-                        let next_coord = (coord.0 + dir.0, coord.1 + dir.1);
-                        let next_row = next_coord.0 as usize;
-                        let next_col = next_coord.1 as usize;
-
-                        if (0..n).contains(&next_row)
-                            && (0..m).contains(&next_col)
-                            && dirpad[next_row][next_col] != ' '
-                        {
-                            let next_state = if next_coord != key2_coord {
-                                (next_coord, dir_key_char)
-                            } else {
-                                (next_coord, 'A')
-                            };
-
-                            let next_cost = {
-                                let mut next_cost = cost + dp[robot - 1][prev_key][dir_key];
-                                if next_coord == key2_coord {
-                                    next_cost += dp[robot - 1][dir_key][key_to_num('A')]
-                                }
-                                next_cost
-                            };
-
-                            let min_cost = min_costs.entry(next_state).or_insert(i64::MAX);
-                            if next_cost < *min_cost {
-                                *min_cost = next_cost;
-                                dijkstra.push(Reverse((next_cost, next_state)));
-                            }
-                        }
-                    }
-                }
+    // Intuition: Dynamic Programming, with Dijkstra's baked into each calculation step
+    //
+    // Press(N, X, Y) = Cost of moving the arm over keypad N from key X to Y and pressing Y
+    //
+    // Press(human, X, Y) = 1 (Human has 0 cost moves)
+    //
+    // Press(N, X, Y) = Find directional path I-J-K on keypad N+1 minimizing:
+    //     Press(N+1, A, I) + Press(N+1, I, J) + Press(N+1, J, K) + Press(N+1, K, A)
+    fn best_move(
+        &mut self,
+        layer: usize,
+        from: char,
+        to: char,
+    ) -> Result<(u128, String), ChainError> {
+        if let Some(best) = self.moves.get(&(layer, from, to)) {
+            return Ok(best.clone());
+        }
 
-                let min_cost = if key1 != key2 {
-                    *min_costs.get(&(key2_coord, 'A')).expect("path exists")
-                } else {
-                    1
-                };
+        let keypad = self.layers[layer].clone();
+        let missing = |key| ChainError::MissingKey { layer, key };
+        let start = keypad.position(from).ok_or_else(|| missing(from))?;
+        let end = keypad.position(to).ok_or_else(|| missing(to))?;
+
+        // State: (arm position on this keypad, last key pressed on the next keypad)
+        let mut min_costs = HashMap::new();
+        let mut prev_states = HashMap::new();
+        min_costs.insert((start, 'A'), 0u128);
+
+        let mut dijkstra = BinaryHeap::new();
+        dijkstra.push(Reverse((0u128, (start, 'A'))));
+
+        while let Some(Reverse((cost, state))) = dijkstra.pop() {
+            if cost > min_costs[&state] {
+                continue;
+            }
+
+            let (coord, prev_key) = state;
+            if prev_key == '\0' {
+                break;
+            }
+
+            let mut next_states = Vec::new();
+            if coord == end {
+                next_states.push(((coord, '\0'), self.cost(layer + 1, prev_key, 'A')?));
+            }
+
+            for dir_key in DIRECTIONS {
+                let dir = direction_of(dir_key);
+                let next_coord = (coord.0 + dir.0, coord.1 + dir.1);
 
-                dp[robot][key1][key2] = min_cost;
+                if keypad.key_at(next_coord).is_some() {
+                    let next_cost = self.cost(layer + 1, prev_key, dir_key)?;
+                    next_states.push(((next_coord, dir_key), next_cost));
+                }
+            }
+
+            for (next_state, step_cost) in next_states {
+                let next_cost = cost
+                    .checked_add(step_cost)
+                    .ok_or(ChainError::Overflow { layer })?;
+                let min_cost = min_costs.entry(next_state).or_insert(u128::MAX);
+                if next_cost < *min_cost {
+                    *min_cost = next_cost;
+                    prev_states.insert(next_state, state);
+                    dijkstra.push(Reverse((next_cost, next_state)));
+                }
             }
         }
+
+        // '\0' marks having pressed A on the next keypad, so the arm over this one pressed `to`
+        let done = (end, '\0');
+        let cost = *min_costs
+            .get(&done)
+            .ok_or(ChainError::Unreachable { layer, from, to })?;
+
+        let mut presses = vec!['A'];
+        let mut state = prev_states[&done];
+        while state != (start, 'A') {
+            presses.push(state.1);
+            state = prev_states[&state];
+        }
+        presses.reverse();
+
+        let best = (cost, presses.into_iter().collect::<String>());
+        self.moves.insert((layer, from, to), best.clone());
+        Ok(best)
     }
 
-    dp
-}
+    fn sequence_cost(&mut self, keys: &str) -> Result<u128, ChainError> {
+        let mut cost = 0;
+        let mut prev = 'A';
+        for key in keys.chars() {
+            cost = self
+                .cost(0, prev, key)?
+                .checked_add(cost)
+                .ok_or(ChainError::Overflow { layer: 0 })?;
+            prev = key;
+        }
+        Ok(cost)
+    }
 
-fn compute_code_cost(code: &String, dp: &Vec<Vec<Vec<i64>>>) -> i64 {
-    let numpad = [
-        ['7', '8', '9'],
-        ['4', '5', '6'],
-        ['1', '2', '3'],
-        [' ', '0', 'A'],
-    ];
-    let n = numpad.len();
-    let m = numpad[0].len();
-    let robots = dp.len() - 1;
-
-    let numeric: i64 = code.trim_end_matches('A').parse().unwrap();
-    let mut sequence: i64 = 0;
-
-    let code = "A".to_string() + code;
-    let code: Vec<_> = code.chars().collect();
-
-    for keys in code.windows(2) {
-        let (key1, key2) = (keys[0], keys[1]);
-        let (key1_coord, key2_coord) = {
-            let mut key1_coord = (0, 0);
-            let mut key2_coord = (0, 0);
-            for (row, keys) in numpad.iter().enumerate() {
-                for (col, key) in keys.iter().enumerate() {
-                    let coord = (row as isize, col as isize);
-                    if *key == key1 {
-                        key1_coord = coord;
-                    }
-                    if *key == key2 {
-                        key2_coord = coord;
-                    }
-                }
+    /// The keys the human presses to type `keys` on the door, by expanding the best moves
+    /// through every layer. Its length is exponential in the number of layers, so it's checked
+    /// against `limit` before expanding anything.
+    fn human_sequence(&mut self, keys: &str, limit: u128) -> Result<String, ChainError> {
+        let length = self.sequence_cost(keys)?;
+        if length > limit {
+            return Err(ChainError::TooLong { length, limit });
+        }
+
+        let mut keys = keys.to_owned();
+
+        for layer in 0..self.human_layer() {
+            let mut next_keys = String::new();
+            let mut prev = 'A';
+            for key in keys.chars() {
+                next_keys += &self.best_move(layer, prev, key)?.1;
+                prev = key;
             }
-            (key1_coord, key2_coord)
-        };
+            keys = next_keys;
+        }
 
-        let mut min_costs = HashMap::new();
-        min_costs.insert((key1_coord, 'A'), 0);
+        Ok(keys)
+    }
 
-        let mut dijkstra = BinaryHeap::new();
-        dijkstra.push(Reverse((0, (key1_coord, 'A'))));
-
-        while !dijkstra.is_empty() {
-            let (cost, state) = dijkstra.pop().unwrap().0;
-            let (coord, prev_key_char) = state;
-            let prev_key = key_to_num(prev_key_char);
-
-            for dir_key_char in ['^', '<', 'v', '>'] {
-                let dir_key = key_to_num(dir_key_char);
-                let dir = match dir_key_char {
-                    '^' => (-1, 0),
-                    '<' => (0, -1),
-                    'v' => (1, 0),
-                    '>' => (0, 1),
-                    _ => unreachable!(),
-                };
+    /// Presses `keys` on the human keypad, and returns what comes out on the door.
+    fn replay(&self, keys: &str) -> Result<String, ChainError> {
+        let mut keys = keys.to_owned();
 
-                let next_coord = (coord.0 + dir.0, coord.1 + dir.1);
-                let next_row = next_coord.0 as usize;
-                let next_col = next_coord.1 as usize;
-
-                if (0..n).contains(&next_row)
-                    && (0..m).contains(&next_col)
-                    && numpad[next_row][next_col] != ' '
-                {
-                    let next_state = if next_coord != key2_coord {
-                        (next_coord, dir_key_char)
-                    } else {
-                        (next_coord, 'A')
-                    };
-
-                    let next_cost = {
-                        let mut next_cost = cost + dp[robots][prev_key][dir_key];
-                        if next_coord == key2_coord {
-                            next_cost += dp[robots][dir_key][key_to_num('A')]
-                        }
-                        next_cost
-                    };
-
-                    let min_cost = min_costs.entry(next_state).or_insert(i64::MAX);
-                    if next_cost < *min_cost {
-                        *min_cost = next_cost;
-                        dijkstra.push(Reverse((next_cost, next_state)));
+        for layer in (0..self.human_layer()).rev() {
+            let keypad = &self.layers[layer];
+            let mut arm = keypad
+                .position('A')
+                .ok_or(ChainError::MissingKey { layer, key: 'A' })?;
+            let mut pressed = String::new();
+
+            for key in keys.chars() {
+                if key == 'A' {
+                    pressed.push(keypad.key_at(arm).ok_or(ChainError::Panicked { layer })?);
+                } else {
+                    let dir = direction_of(key);
+                    arm = (arm.0 + dir.0, arm.1 + dir.1);
+                    if keypad.key_at(arm).is_none() {
+                        return Err(ChainError::Panicked { layer });
                     }
                 }
             }
-        }
 
-        let min_cost = if key1 != key2 {
-            *min_costs.get(&(key2_coord, 'A')).expect("path exists")
-        } else {
-            1
-        };
+            keys = pressed;
+        }
 
-        sequence += min_cost;
+        Ok(keys)
     }
+}
 
-    sequence * numeric
+fn compute_complexity_sum(codes: &[String], chain: &mut KeypadChain) -> Result<u128, ChainError> {
+    let mut sum = 0;
+    for code in codes.iter() {
+        let numeric: u128 = code.trim_end_matches('A').parse().unwrap();
+        sum = chain
+            .sequence_cost(code)?
+            .checked_mul(numeric)
+            .and_then(|complexity| complexity.checked_add(sum))
+            .ok_or(ChainError::Overflow { layer: 0 })?;
+    }
+    Ok(sum)
 }
 
-fn part_one(codes: &Vec<String>) {
-    let dp = compute_keypress_costs(2);
-    let sum: i64 = codes.iter().map(|code| compute_code_cost(code, &dp)).sum();
+fn part_one(codes: &[String]) {
+    match compute_complexity_sum(codes, &mut KeypadChain::door(2)) {
+        Ok(sum) => println!("{}", sum),
+        Err(err) => eprintln!("error: {}", err),
+    }
+}
 
-    println!("{}", sum);
+fn part_two(codes: &[String], robots: usize) {
+    match compute_complexity_sum(codes, &mut KeypadChain::door(robots)) {
+        Ok(sum) => println!("{}", sum),
+        Err(err) => eprintln!("error: {}", err),
+    }
 }
 
-fn part_two(codes: &Vec<String>) {
-    let dp = compute_keypress_costs(25);
-    let sum: i64 = codes.iter().map(|code| compute_code_cost(code, &dp)).sum();
+/// Prints the human key sequence for every code, and checks it by replaying it through the chain
+fn print_sequences(
+    codes: &[String],
+    chain: &mut KeypadChain,
+    limit: u128,
+) -> Result<(), ChainError> {
+    for code in codes.iter() {
+        let sequence = match chain.human_sequence(code, limit) {
+            Err(err @ ChainError::TooLong { .. }) => {
+                println!("{}: {}", code, err);
+                continue;
+            }
+            sequence => sequence?,
+        };
+        let cost = chain.sequence_cost(code)?;
+        let replayed = chain.replay(&sequence)?;
+
+        println!("{}: {}", code, sequence);
+        println!("    length {} (expected {})", sequence.len(), cost);
+        println!("    replays as {}", replayed);
+    }
 
-    println!("{}", sum);
+    Ok(())
 }
 
 fn main() -> std::io::Result<()> {
@@ -270,8 +327,19 @@ fn main() -> std::io::Result<()> {
 
     let codes: Vec<String> = input.lines().map(str::to_string).collect();
 
-    part_one(&codes);
-    part_two(&codes);
+    match std::env::args().nth(1).as_deref() {
+        Some("sequence") => {
+            let robots = aoc::option("robots")?.unwrap_or(2);
+            let limit = aoc::option("max-length")?.unwrap_or(MAX_SEQUENCE_LENGTH);
+            if let Err(err) = print_sequences(&codes, &mut KeypadChain::door(robots), limit) {
+                eprintln!("error: {}", err);
+            }
+        }
+        _ => {
+            part_one(&codes);
+            part_two(&codes, aoc::option("robots")?.unwrap_or(25));
+        }
+    }
 
     Ok(())
 }
@@ -1,64 +1,62 @@
-use std::collections::HashMap;
-
-fn compute_next_secret(mut secret: i64) -> i64 {
-    const M: i64 = 1 << 24;
+fn compute_next_secret(mut secret: u64) -> u64 {
+    const M: u64 = 1 << 24;
     secret = (secret ^ (secret << 6)) % M; // step 1
     secret = (secret ^ (secret >> 5)) % M; // step 2
     secret = (secret ^ (secret << 11)) % M; // step 3
     secret
 }
 
-fn part_one(secrets: &Vec<i64>) {
-    let mut sum = 0;
-    for mut secret in secrets.iter().cloned() {
-        for _ in 0..2000 {
-            secret = compute_next_secret(secret);
+fn part_one(secrets: &Vec<u64>) {
+    let mut secrets = secrets.clone();
+
+    // Step every buyer at once, so that the inner loop is a plain map over a slice which the
+    // compiler can vectorize
+    for _ in 0..2000 {
+        for secret in secrets.iter_mut() {
+            *secret = compute_next_secret(*secret);
         }
-        sum += secret;
     }
 
+    let sum: u64 = secrets.iter().sum();
     println!("{}", sum);
 }
 
-fn part_two(secrets: &Vec<i64>) {
-    let mut sequence_sums = HashMap::new();
+fn part_two(secrets: &Vec<u64>) {
+    // Each price change is in -9..=9, so a sequence of four is a base 19 number with four digits
+    const SEQUENCES: usize = 19 * 19 * 19 * 19;
+
+    let mut sequence_sums = vec![0; SEQUENCES];
+    // Last buyer (plus one) to see each sequence, since only the first occurrence counts
+    let mut seen_by = vec![0; SEQUENCES];
 
-    // O(secrets * generations) shouldn't be super bad, but it takes a while to run for some reason?
+    for (buyer, mut secret) in secrets.iter().cloned().enumerate() {
+        let stamp = buyer + 1;
+        let mut price = secret % 10;
+        let mut sequence = 0;
 
-    for mut secret in secrets.iter().cloned() {
-        let mut prices = Vec::new();
-        for _ in 0..=2000 {
-            prices.push(secret % 10);
+        for generation in 1..=2000 {
             secret = compute_next_secret(secret);
-        }
+            let prev_price = price;
+            price = secret % 10;
 
-        let mut sequences = HashMap::new();
-        for group in prices.windows(5) {
-            let a = group[1] - group[0];
-            let b = group[2] - group[1];
-            let c = group[3] - group[2];
-            let d = group[4] - group[3];
-            let sequence = (a, b, c, d);
-            let price = group[4];
+            let change = (price + 9 - prev_price) as usize;
+            sequence = (sequence * 19 + change) % SEQUENCES;
 
-            if !sequences.contains_key(&sequence) {
-                sequences.insert(sequence, price);
+            if generation >= 4 && seen_by[sequence] != stamp {
+                seen_by[sequence] = stamp;
+                sequence_sums[sequence] += price;
             }
         }
-
-        for (&sequence, &price) in sequences.iter() {
-            *sequence_sums.entry(sequence).or_insert(0) += price;
-        }
     }
 
-    let max_sum = sequence_sums.values().max().unwrap();
+    let max_sum = sequence_sums.iter().max().unwrap();
     println!("{}", max_sum);
 }
 
 fn main() -> std::io::Result<()> {
     let input = aoc::read_input(2024, 22)?;
 
-    let secrets: Vec<i64> = input.lines().map(|line| line.parse().unwrap()).collect();
+    let secrets: Vec<u64> = input.lines().map(|line| line.parse().unwrap()).collect();
 
     part_one(&secrets);
     part_two(&secrets);
@@ -1,4 +1,6 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
+
+use aoc::circuit::{self, Circuit, Gate};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Input {
@@ -6,254 +8,154 @@ struct Input {
     value: u64,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Gate {
-    And,
-    Or,
-    Xor,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct Wiring {
-    gate: Gate,
-    input1: String,
-    input2: String,
-    output: String,
-}
-
-fn create_wire_list(wirings: &Vec<Wiring>) -> Vec<String> {
-    let mut wire_list = BTreeSet::new();
+fn part_one(inputs: &Vec<Input>, circuit: &Circuit) {
+    let inputs: HashMap<_, _> = inputs
+        .iter()
+        .map(|input| (input.wire.clone(), input.value))
+        .collect();
 
-    for wiring in wirings.iter() {
-        wire_list.insert(wiring.input1.clone());
-        wire_list.insert(wiring.input2.clone());
-        wire_list.insert(wiring.output.clone());
+    match circuit.evaluate(&inputs) {
+        Ok(values) => println!("{}", circuit::read_number(&values, 'z')),
+        Err(err) => eprintln!("error: {}", err),
     }
-
-    wire_list.into_iter().collect()
 }
 
-fn create_gate_list(
-    wirings: &Vec<Wiring>,
-    wire_list: &Vec<String>,
-) -> Vec<Vec<(Gate, usize, usize)>> {
-    let wires = wire_list.len();
-    let mut gate_list = vec![Vec::new(); wires];
+/// Checks the circuit against random and single bit additions, including a full carry chain.
+fn is_adder(circuit: &Circuit, bits: usize) -> bool {
+    let mask = (1 << bits) - 1;
+    let mut tests = vec![(mask, 1), (mask, mask)];
 
-    for wiring in wirings.iter() {
-        let gate = wiring.gate;
-        let input1 = wire_list.binary_search(&wiring.input1).unwrap();
-        let input2 = wire_list.binary_search(&wiring.input2).unwrap();
-        let output = wire_list.binary_search(&wiring.output).unwrap();
+    for bit in 0..bits {
+        tests.push((1 << bit, 0));
+        tests.push((0, 1 << bit));
+        tests.push((1 << bit, 1 << bit));
+    }
 
-        gate_list[input1].push((gate, input2, output));
-        gate_list[input2].push((gate, input1, output));
+    // xorshift64, seeded arbitrarily so runs are reproducible
+    let mut state: u64 = 0x2024_1224;
+    let mut random = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state & mask
+    };
+    for _ in 0..100 {
+        tests.push((random(), random()));
     }
 
-    gate_list
+    tests
+        .into_iter()
+        .all(|(x, y)| circuit.evaluate_xy(x, y) == Ok(x + y))
 }
 
-fn part_one(inputs: &Vec<Input>, wirings: &Vec<Wiring>) {
-    let wire_list = create_wire_list(wirings);
-    let gate_list = create_gate_list(wirings, &wire_list);
-    let wires = wire_list.len();
-
-    let mut wire_values = vec![None; wires];
-    let mut wires_to_process = vec![];
-
-    for input in inputs.iter() {
-        if let Ok(wire) = wire_list.binary_search(&input.wire) {
-            wires_to_process.push((wire, input.value));
-        }
-    }
+/// Flags gate outputs that break the structure of a ripple-carry adder, where for every bit i > 0:
+/// - z_i = (x_i XOR y_i) XOR carry_i
+/// - carry_i+1 = (x_i AND y_i) OR ((x_i XOR y_i) AND carry_i)
+/// - bit 0 is a half adder, and the last z is the final carry
+fn find_miswired_outputs(circuit: &Circuit, bits: usize) -> BTreeSet<String> {
+    let wirings = circuit.wirings();
+    let last_z = format!("z{:02}", bits);
+    let is_input = |wire: &str| wire.starts_with('x') || wire.starts_with('y');
+    let is_first_bit = |wire: &str| wire == "x00" || wire == "y00";
+    let feeds = |output: &str, gate: Gate| {
+        wirings.iter().any(|wiring| {
+            wiring.gate == gate && (wiring.input1 == output || wiring.input2 == output)
+        })
+    };
 
-    while !wires_to_process.is_empty() {
-        let (wire, value) = wires_to_process.pop().unwrap();
+    let mut miswired = BTreeSet::new();
 
-        if wire_values[wire].is_some() {
-            panic!("conflict wtf (outputs should only be set once)");
-        }
-        wire_values[wire] = Some(value);
+    for wiring in wirings.iter() {
+        let output = wiring.output.as_str();
+        let is_z = output.starts_with('z');
+        let from_inputs = is_input(&wiring.input1) && is_input(&wiring.input2);
+        let first_bit = is_first_bit(&wiring.input1) && is_first_bit(&wiring.input2);
+
+        let is_miswired = match wiring.gate {
+            _ if output == last_z => wiring.gate != Gate::Or,
+            _ if is_z && wiring.gate != Gate::Xor => true,
+            Gate::Xor if first_bit => output != "z00",
+            Gate::Xor if from_inputs => !feeds(output, Gate::Xor),
+            Gate::Xor => !is_z,
+            Gate::And if first_bit => feeds(output, Gate::Or),
+            Gate::And => !feeds(output, Gate::Or),
+            Gate::Or => !feeds(output, Gate::Xor) || !feeds(output, Gate::And),
+        };
 
-        for &(gate, other, output) in gate_list[wire].iter() {
-            if let Some(other_value) = wire_values[other] {
-                let result = match gate {
-                    Gate::And => value & other_value,
-                    Gate::Or => value | other_value,
-                    Gate::Xor => value ^ other_value,
-                };
-                wires_to_process.push((output, result));
-            }
+        if is_miswired {
+            miswired.insert(wiring.output.clone());
         }
     }
 
-    let mut num = 0;
-
-    for (wire, value) in wire_values.iter().enumerate() {
-        let wire = &wire_list[wire];
-        let value = value.expect("z wire has value");
-
-        if wire.starts_with('z') {
-            let bit: u64 = wire.trim_start_matches('z').parse().unwrap();
+    miswired
+}
 
-            num |= value << bit;
+/// Tries every way of pairing up the suspect wires, returning the first pairing that turns the
+/// circuit into a working adder.
+fn find_swaps<'a>(
+    circuit: &mut Circuit,
+    bits: usize,
+    suspects: &mut Vec<&'a str>,
+    swaps: &mut Vec<(&'a str, &'a str)>,
+) -> bool {
+    let Some(first) = suspects.pop() else {
+        return is_adder(circuit, bits);
+    };
+
+    for idx in 0..suspects.len() {
+        let second = suspects.remove(idx);
+
+        circuit.swap_outputs(first, second);
+        swaps.push((first, second));
+
+        if find_swaps(circuit, bits, suspects, swaps) {
+            return true;
         }
+
+        swaps.pop();
+        circuit.swap_outputs(first, second);
+        suspects.insert(idx, second);
     }
 
-    println!("{}", num);
+    suspects.push(first);
+    false
 }
 
-fn part_two(_inputs: &Vec<Input>, wirings: &Vec<Wiring>) {
-    // Perform swaps first for manual analysis
-    let mut wirings = wirings.clone();
-    let n = wirings.len();
-
-    let swaps = [
-        ("qnw", "z15"),
-        ("cqr", "z20"),
-        ("vkg", "z37"),
-        ("ncd", "nfj"), // at z27
-    ];
-    for swap in swaps {
-        let mut i = 0;
-        let mut j = 0;
-        for k in 0..n {
-            if wirings[k].output == swap.0 {
-                i = k;
-            }
-            if wirings[k].output == swap.1 {
-                j = k;
-            }
-        }
-
-        let tmp = std::mem::take(&mut wirings[i].output);
-        wirings[i].output = std::mem::take(&mut wirings[j].output);
-        wirings[j].output = tmp;
+/// Repairs the circuit in place, returning the swapped wires in sorted order.
+fn repair_adder(circuit: &mut Circuit, bits: usize) -> Result<Vec<String>, String> {
+    let miswired = find_miswired_outputs(circuit, bits);
+    let mut suspects: Vec<_> = miswired.iter().map(String::as_str).collect();
+    let mut swaps = Vec::new();
+
+    if !find_swaps(circuit, bits, &mut suspects, &mut swaps) {
+        return Err(format!(
+            "no pairing of the miswired outputs {:?} produces a working adder",
+            miswired
+        ));
     }
 
     let mut swaps: Vec<_> = swaps
         .into_iter()
-        .map(|(s1, s2)| [s1, s2])
-        .flatten()
+        .flat_map(|(s1, s2)| [s1.to_owned(), s2.to_owned()])
         .collect();
     swaps.sort();
-    let swaps = swaps.join(",");
-    println!("\nPerformed swaps: {}", swaps);
-
-    // Generate Graphviz DOT script to visualize the circuit, manually detect invalid patterns
-    let mut x_inputs = BTreeSet::new();
-    let mut y_inputs = BTreeSet::new();
-    let mut z_outputs = BTreeSet::new();
-
-    let mut and_outputs = BTreeSet::new();
-    let mut or_outputs = BTreeSet::new();
-    let mut xor_outputs = BTreeSet::new();
-
-    // Assumption: x-- and y-- will never be used as outputs
-    // Assumption: z-- will never be used as inputs
-
-    for wiring in wirings.iter() {
-        for input in [&wiring.input1, &wiring.input2] {
-            if input.starts_with('x') {
-                x_inputs.insert(input.clone());
-            }
-            if input.starts_with('y') {
-                y_inputs.insert(input.clone());
-            }
-        }
-
-        let output = wiring.output.clone();
-
-        if output.starts_with('z') {
-            z_outputs.insert(output.clone());
-        }
-
-        match wiring.gate {
-            Gate::And => and_outputs.insert(output),
-            Gate::Or => or_outputs.insert(output),
-            Gate::Xor => xor_outputs.insert(output),
-        };
-    }
-
-    let and_outputs = Vec::from_iter(and_outputs).join("; ");
-    let and_outputs_styles = format!(
-        r#"subgraph and_outputs {{
-    node [style=filled, color=pink];
-    {and_outputs};
-}}"#
-    );
-
-    let or_outputs = Vec::from_iter(or_outputs).join("; ");
-    let or_outputs_styles = format!(
-        r#"subgraph or_outputs {{
-    node [style=filled, color=lightblue];
-    {or_outputs};
-}}"#
-    );
-
-    let xor_outputs = Vec::from_iter(xor_outputs).join("; ");
-    let xor_outputs_styles = format!(
-        r#"subgraph xor_outputs {{
-    node [style=filled, color=lightgreen];
-    {xor_outputs};
-}}"#
-    );
-
-    let x_inputs = Vec::from_iter(x_inputs).join(" -> ");
-    let x_inputs_styles = format!(
-        r#"subgraph x_inputs {{
-    node [style=filled, color=lightgray];
-    edge [style=invis];
-    {x_inputs};
-}}"#
-    );
-
-    let y_inputs = Vec::from_iter(y_inputs).join(" -> ");
-    let y_inputs_styles = format!(
-        r#"subgraph y_inputs {{
-    node [style=filled, color=lightgray];
-    edge [style=invis];
-    {y_inputs};
-}}"#
-    );
-
-    let z_outputs = Vec::from_iter(z_outputs).join(" -> ");
-    let z_outputs_styles = format!(
-        r#"subgraph z_outputs {{
-    node [style=filled, color=lightgray];
-    edge [style=invis];
-    {z_outputs};
-}}"#
-    );
+    Ok(swaps)
+}
 
-    let dot_edges: Vec<_> = wirings
+fn count_bits(inputs: &[Input]) -> usize {
+    inputs
         .iter()
-        .map(|wiring| {
-            format!(
-                "{0} -> {2}; {1} -> {2};",
-                wiring.input1, wiring.input2, wiring.output
-            )
-        })
-        .collect();
-    let dot_edges = dot_edges.join("\n");
-
-    let dot_script = format!(
-        r#"
-digraph circuit {{
-    {and_outputs_styles}
-    {or_outputs_styles}
-    {xor_outputs_styles}
-
-    {x_inputs_styles}
-    {y_inputs_styles}
-    {z_outputs_styles}
+        .filter(|input| input.wire.starts_with('x'))
+        .count()
+}
 
-    {dot_edges}
-}}
-"#
-    );
+fn part_two(inputs: &Vec<Input>, circuit: &Circuit) {
+    let mut circuit = circuit.clone();
 
-    println!("{dot_script}");
+    match repair_adder(&mut circuit, count_bits(inputs)) {
+        Ok(swaps) => println!("{}", swaps.join(",")),
+        Err(err) => eprintln!("error: {}", err),
+    }
 }
 
 fn main() -> std::io::Result<()> {
@@ -269,36 +171,29 @@ fn main() -> std::io::Result<()> {
             Input { wire, value }
         })
         .collect();
-    let wirings: Vec<Wiring> = wirings
-        .lines()
-        .map(|line| {
-            let words: Vec<_> = line.split_whitespace().collect();
-            let input1 = words[0];
-            let gate = words[1];
-            let input2 = words[2];
-            let output = words[4];
-
-            let gate = match gate {
-                "AND" => Gate::And,
-                "OR" => Gate::Or,
-                "XOR" => Gate::Xor,
-                _ => unreachable!(),
-            };
-            let input1 = input1.to_owned();
-            let input2 = input2.to_owned();
-            let output = output.to_owned();
-
-            Wiring {
-                gate,
-                input1,
-                input2,
-                output,
+    let mut circuit = match Circuit::parse(wirings) {
+        Ok(circuit) => circuit,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return Ok(());
+        }
+    };
+
+    match std::env::args().nth(1).as_deref() {
+        // Graphviz DOT script, to visualize the circuit and spot invalid patterns by eye
+        Some("dot") => {
+            if std::env::args().nth(2).as_deref() == Some("fixed") {
+                if let Err(err) = repair_adder(&mut circuit, count_bits(&inputs)) {
+                    eprintln!("error: {}", err);
+                }
             }
-        })
-        .collect();
-
-    part_one(&inputs, &wirings);
-    part_two(&inputs, &wirings);
+            print!("{}", circuit.to_dot());
+        }
+        _ => {
+            part_one(&inputs, &circuit);
+            part_two(&inputs, &circuit);
+        }
+    }
 
     Ok(())
 }
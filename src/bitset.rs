@@ -0,0 +1,196 @@
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct BitSet {
+    words: Vec<u64>,
+    capacity: usize,
+}
+
+impl BitSet {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            words: vec![0; capacity.div_ceil(64)],
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns `false` if `idx` was already in the set.
+    pub fn insert(&mut self, idx: usize) -> bool {
+        assert!(idx < self.capacity, "index {idx} out of range");
+        let word = &mut self.words[idx / 64];
+        let mask = 1 << (idx % 64);
+        let inserted = *word & mask == 0;
+        *word |= mask;
+        inserted
+    }
+
+    /// Returns `true` if `idx` was in the set.
+    pub fn remove(&mut self, idx: usize) -> bool {
+        assert!(idx < self.capacity, "index {idx} out of range");
+        let word = &mut self.words[idx / 64];
+        let mask = 1 << (idx % 64);
+        let removed = *word & mask != 0;
+        *word &= !mask;
+        removed
+    }
+
+    pub fn contains(&self, idx: usize) -> bool {
+        idx < self.capacity && self.words[idx / 64] & (1 << (idx % 64)) != 0
+    }
+
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    pub fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(i * 64 + bit)
+            })
+        })
+    }
+}
+
+/// A set of grid cells, optionally split into `layers` (e.g. one per facing direction).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct BitGrid {
+    rows: usize,
+    cols: usize,
+    layers: usize,
+    bits: BitSet,
+}
+
+impl BitGrid {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Self::with_layers(rows, cols, 1)
+    }
+
+    pub fn with_layers(rows: usize, cols: usize, layers: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            layers,
+            bits: BitSet::new(rows * cols * layers),
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    fn index(&self, row: usize, col: usize, layer: usize) -> usize {
+        assert!(
+            row < self.rows && col < self.cols && layer < self.layers,
+            "({row}, {col}, {layer}) out of range"
+        );
+        (row * self.cols + col) * self.layers + layer
+    }
+
+    pub fn insert(&mut self, row: usize, col: usize) -> bool {
+        self.insert_at(row, col, 0)
+    }
+
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        self.contains_at(row, col, 0)
+    }
+
+    pub fn remove(&mut self, row: usize, col: usize) -> bool {
+        self.remove_at(row, col, 0)
+    }
+
+    pub fn insert_at(&mut self, row: usize, col: usize, layer: usize) -> bool {
+        let idx = self.index(row, col, layer);
+        self.bits.insert(idx)
+    }
+
+    pub fn contains_at(&self, row: usize, col: usize, layer: usize) -> bool {
+        row < self.rows
+            && col < self.cols
+            && layer < self.layers
+            && self.bits.contains(self.index(row, col, layer))
+    }
+
+    pub fn remove_at(&mut self, row: usize, col: usize, layer: usize) -> bool {
+        let idx = self.index(row, col, layer);
+        self.bits.remove(idx)
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.clear();
+    }
+
+    /// Number of (row, col, layer) entries in the set.
+    pub fn count(&self) -> usize {
+        self.bits.count()
+    }
+
+    /// Number of cells present in at least one layer.
+    pub fn count_cells(&self) -> usize {
+        (0..self.rows * self.cols)
+            .filter(|cell| {
+                (0..self.layers).any(|layer| self.bits.contains(cell * self.layers + layer))
+            })
+            .count()
+    }
+}
+
+/// A set over `0..capacity` that can be cleared in constant time, by bumping a generation
+/// counter instead of touching every entry. Useful when the same search runs many times.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DenseSet {
+    stamps: Vec<u32>,
+    generation: u32,
+}
+
+impl DenseSet {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            stamps: vec![0; capacity],
+            generation: 1,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.stamps.len()
+    }
+
+    /// Returns `false` if `idx` was already in the set.
+    pub fn insert(&mut self, idx: usize) -> bool {
+        let inserted = self.stamps[idx] != self.generation;
+        self.stamps[idx] = self.generation;
+        inserted
+    }
+
+    pub fn contains(&self, idx: usize) -> bool {
+        self.stamps.get(idx) == Some(&self.generation)
+    }
+
+    pub fn clear(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+
+        // Stale stamps could collide with the new generation after wrapping around
+        if self.generation == 0 {
+            self.stamps.fill(0);
+            self.generation = 1;
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::{BTreeSet, HashMap};
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Gate {
+    And,
+    Or,
+    Xor,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Wiring {
+    pub gate: Gate,
+    pub input1: String,
+    pub input2: String,
+    pub output: String,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum CircuitError {
+    #[error("failed to parse wiring `{0}`")]
+    Parse(String),
+    #[error("wire {0} is driven more than once")]
+    Conflict(String),
+    #[error("wire {0} is part of a cycle")]
+    Cycle(String),
+    #[error("wire {0} is neither an input nor a gate output")]
+    Undriven(String),
+}
+
+impl std::str::FromStr for Wiring {
+    type Err = CircuitError;
+
+    /// Parses `input1 GATE input2 -> output`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<_> = s.split_whitespace().collect();
+        let [input1, gate, input2, "->", output] = words[..] else {
+            return Err(CircuitError::Parse(s.to_owned()));
+        };
+
+        let gate = match gate {
+            "AND" => Gate::And,
+            "OR" => Gate::Or,
+            "XOR" => Gate::Xor,
+            _ => return Err(CircuitError::Parse(s.to_owned())),
+        };
+
+        Ok(Self {
+            gate,
+            input1: input1.to_owned(),
+            input2: input2.to_owned(),
+            output: output.to_owned(),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Circuit {
+    wirings: Vec<Wiring>,
+    drivers: HashMap<String, usize>,
+}
+
+impl Circuit {
+    pub fn new(wirings: Vec<Wiring>) -> Result<Self, CircuitError> {
+        let mut drivers = HashMap::new();
+        for (idx, wiring) in wirings.iter().enumerate() {
+            if drivers.insert(wiring.output.clone(), idx).is_some() {
+                return Err(CircuitError::Conflict(wiring.output.clone()));
+            }
+        }
+
+        Ok(Self { wirings, drivers })
+    }
+
+    pub fn parse(s: &str) -> Result<Self, CircuitError> {
+        let wirings: Result<Vec<_>, _> = s.lines().map(|line| line.parse()).collect();
+        Self::new(wirings?)
+    }
+
+    pub fn wirings(&self) -> &[Wiring] {
+        &self.wirings
+    }
+
+    /// Wires that aren't driven by any gate, in sorted order.
+    pub fn inputs(&self) -> Vec<&str> {
+        let inputs: BTreeSet<_> = self
+            .wirings
+            .iter()
+            .flat_map(|wiring| [&wiring.input1, &wiring.input2])
+            .filter(|wire| !self.drivers.contains_key(*wire))
+            .map(String::as_str)
+            .collect();
+
+        inputs.into_iter().collect()
+    }
+
+    /// Gate outputs, in sorted order.
+    pub fn outputs(&self) -> Vec<&str> {
+        let outputs: BTreeSet<_> = self.drivers.keys().map(String::as_str).collect();
+        outputs.into_iter().collect()
+    }
+
+    pub fn swap_outputs(&mut self, wire1: &str, wire2: &str) {
+        let (Some(idx1), Some(idx2)) = (self.drivers.get(wire1), self.drivers.get(wire2)) else {
+            return;
+        };
+        let (idx1, idx2) = (*idx1, *idx2);
+
+        self.wirings[idx1].output = wire2.to_owned();
+        self.wirings[idx2].output = wire1.to_owned();
+        self.drivers.insert(wire1.to_owned(), idx2);
+        self.drivers.insert(wire2.to_owned(), idx1);
+    }
+
+    /// Computes the value of every gate output, given a value for each input wire.
+    pub fn evaluate(
+        &self,
+        inputs: &HashMap<String, u64>,
+    ) -> Result<HashMap<String, u64>, CircuitError> {
+        if let Some(wire) = inputs.keys().find(|wire| self.drivers.contains_key(*wire)) {
+            return Err(CircuitError::Conflict(wire.clone()));
+        }
+
+        let mut values = HashMap::new();
+        for wire in self.drivers.keys() {
+            self.evaluate_wire(wire, inputs, &mut values)?;
+        }
+
+        Ok(values
+            .into_iter()
+            .map(|(wire, value)| (wire.to_owned(), value.unwrap()))
+            .collect())
+    }
+
+    fn evaluate_wire<'a>(
+        &'a self,
+        wire: &'a str,
+        inputs: &HashMap<String, u64>,
+        values: &mut HashMap<&'a str, Option<u64>>,
+    ) -> Result<u64, CircuitError> {
+        if let Some(value) = inputs.get(wire) {
+            return Ok(*value);
+        }
+
+        match values.get(wire) {
+            Some(Some(value)) => return Ok(*value),
+            Some(None) => return Err(CircuitError::Cycle(wire.to_owned())),
+            None => (),
+        }
+
+        let Some(&idx) = self.drivers.get(wire) else {
+            return Err(CircuitError::Undriven(wire.to_owned()));
+        };
+        let wiring = &self.wirings[idx];
+
+        values.insert(wire, None); // mark as in progress
+        let input1 = self.evaluate_wire(&wiring.input1, inputs, values)?;
+        let input2 = self.evaluate_wire(&wiring.input2, inputs, values)?;
+        let value = match wiring.gate {
+            Gate::And => input1 & input2,
+            Gate::Or => input1 | input2,
+            Gate::Xor => input1 ^ input2,
+        };
+        values.insert(wire, Some(value));
+
+        Ok(value)
+    }
+
+    /// Evaluates the circuit with the bits of `x` and `y` on wires x00, x01, ... and y00, y01, ...
+    /// and returns the number on wires z00, z01, ...
+    pub fn evaluate_xy(&self, x: u64, y: u64) -> Result<u64, CircuitError> {
+        let bit_of = |wire: &str, prefix: char, num: u64| {
+            let bit: u32 = wire.strip_prefix(prefix)?.parse().ok()?;
+            Some(num.checked_shr(bit).unwrap_or(0) & 1)
+        };
+
+        let mut inputs = HashMap::new();
+        for wire in self.inputs() {
+            if let Some(value) = bit_of(wire, 'x', x).or_else(|| bit_of(wire, 'y', y)) {
+                inputs.insert(wire.to_owned(), value);
+            }
+        }
+
+        let values = self.evaluate(&inputs)?;
+        Ok(read_number(&values, 'z'))
+    }
+
+    /// Graphviz DOT script with gates coloured by type, and x, y and z wires kept in order.
+    pub fn to_dot(&self) -> String {
+        let mut x_inputs = BTreeSet::new();
+        let mut y_inputs = BTreeSet::new();
+        let mut z_outputs = BTreeSet::new();
+
+        let mut and_outputs = BTreeSet::new();
+        let mut or_outputs = BTreeSet::new();
+        let mut xor_outputs = BTreeSet::new();
+
+        for wiring in self.wirings.iter() {
+            for input in [&wiring.input1, &wiring.input2] {
+                if input.starts_with('x') {
+                    x_inputs.insert(input.as_str());
+                }
+                if input.starts_with('y') {
+                    y_inputs.insert(input.as_str());
+                }
+            }
+
+            let output = wiring.output.as_str();
+
+            if output.starts_with('z') {
+                z_outputs.insert(output);
+            }
+
+            match wiring.gate {
+                Gate::And => and_outputs.insert(output),
+                Gate::Or => or_outputs.insert(output),
+                Gate::Xor => xor_outputs.insert(output),
+            };
+        }
+
+        let styled = |name: &str, color: &str, wires: BTreeSet<&str>| {
+            let wires = Vec::from_iter(wires).join("; ");
+            format!(
+                r#"    subgraph {name} {{
+        node [style=filled, color={color}];
+        {wires};
+    }}
+"#
+            )
+        };
+
+        let chained = |name: &str, wires: BTreeSet<&str>| {
+            let wires = Vec::from_iter(wires).join(" -> ");
+            format!(
+                r#"    subgraph {name} {{
+        node [style=filled, color=lightgray];
+        edge [style=invis];
+        {wires};
+    }}
+"#
+            )
+        };
+
+        let mut dot_script = String::from("digraph circuit {\n");
+        dot_script += &styled("and_outputs", "pink", and_outputs);
+        dot_script += &styled("or_outputs", "lightblue", or_outputs);
+        dot_script += &styled("xor_outputs", "lightgreen", xor_outputs);
+        dot_script += "\n";
+        dot_script += &chained("x_inputs", x_inputs);
+        dot_script += &chained("y_inputs", y_inputs);
+        dot_script += &chained("z_outputs", z_outputs);
+        dot_script += "\n";
+
+        for wiring in self.wirings.iter() {
+            dot_script += &format!(
+                "    {0} -> {2}; {1} -> {2};\n",
+                wiring.input1, wiring.input2, wiring.output
+            );
+        }
+
+        dot_script += "}\n";
+        dot_script
+    }
+}
+
+/// Reads the number whose bits are on the wires `{prefix}00`, `{prefix}01`, ...
+pub fn read_number(values: &HashMap<String, u64>, prefix: char) -> u64 {
+    let mut num = 0;
+
+    for (wire, value) in values.iter() {
+        if let Some(bit) = wire.strip_prefix(prefix) {
+            if let Ok(bit) = bit.parse::<u32>() {
+                num |= value.checked_shl(bit).unwrap_or(0);
+            }
+        }
+    }
+
+    num
+}
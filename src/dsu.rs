@@ -0,0 +1,103 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisjointSet {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    components: usize,
+}
+
+impl DisjointSet {
+    pub fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+            size: vec![1; len],
+            components: len,
+        }
+    }
+
+    /// Builds a set over the cells of `grid`, where cell `(row, col)` is element
+    /// `row * cols + col`, and joins orthogonal neighbours for which `connected` holds.
+    pub fn from_grid<T>(grid: &[Vec<T>], connected: impl Fn(&T, &T) -> bool) -> Self {
+        let n = grid.len();
+        let m = grid.first().map_or(0, |row| row.len());
+        let mut set = Self::new(n * m);
+
+        for row in 0..n {
+            for col in 0..m {
+                if row + 1 < n && connected(&grid[row][col], &grid[row + 1][col]) {
+                    set.union(row * m + col, (row + 1) * m + col);
+                }
+                if col + 1 < m && connected(&grid[row][col], &grid[row][col + 1]) {
+                    set.union(row * m + col, row * m + col + 1);
+                }
+            }
+        }
+
+        set
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+
+        // Path compression
+        let mut x = x;
+        while self.parent[x] != root {
+            let next = self.parent[x];
+            self.parent[x] = root;
+            x = next;
+        }
+
+        root
+    }
+
+    /// Returns `false` if `a` and `b` were already in the same component.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let mut a = self.find(a);
+        let mut b = self.find(b);
+        if a == b {
+            return false;
+        }
+
+        // Union by size
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        self.components -= 1;
+
+        true
+    }
+
+    pub fn same(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    pub fn component_size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.components
+    }
+
+    /// Sizes of every component, keyed by the component's root.
+    pub fn component_sizes(&self) -> Vec<(usize, usize)> {
+        (0..self.len())
+            .filter(|&x| self.parent[x] == x)
+            .map(|root| (root, self.size[root]))
+            .collect()
+    }
+}
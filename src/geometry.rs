@@ -0,0 +1,81 @@
+pub type Point = (i64, i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Location {
+    Inside,
+    Boundary,
+    Outside,
+}
+
+fn edges(vertices: &[Point]) -> impl Iterator<Item = (Point, Point)> + '_ {
+    vertices
+        .iter()
+        .copied()
+        .zip(vertices.iter().copied().cycle().skip(1))
+}
+
+/// Twice the area enclosed by `vertices` (shoelace formula), which is always an integer.
+pub fn double_area(vertices: &[Point]) -> i64 {
+    edges(vertices)
+        .map(|((x1, y1), (x2, y2))| x1 * y2 - x2 * y1)
+        .sum::<i64>()
+        .abs()
+}
+
+pub fn area(vertices: &[Point]) -> f64 {
+    double_area(vertices) as f64 / 2.0
+}
+
+/// Number of lattice points lying on the edges of the polygon.
+pub fn boundary_points(vertices: &[Point]) -> i64 {
+    edges(vertices)
+        .map(|((x1, y1), (x2, y2))| gcd((x2 - x1).abs(), (y2 - y1).abs()))
+        .sum()
+}
+
+/// Number of lattice points strictly inside the polygon, using Pick's theorem:
+/// A = I + B/2 - 1
+pub fn interior_points(vertices: &[Point]) -> i64 {
+    (double_area(vertices) - boundary_points(vertices) + 2) / 2
+}
+
+pub fn locate(point: Point, vertices: &[Point]) -> Location {
+    let (px, py) = point;
+    let mut is_inside = false;
+
+    for ((x1, y1), (x2, y2)) in edges(vertices) {
+        // Exact integer arithmetic, wide enough that no product can overflow
+        let cross = (x2 - x1) as i128 * (py - y1) as i128 - (px - x1) as i128 * (y2 - y1) as i128;
+
+        if cross == 0
+            && (x1.min(x2)..=x1.max(x2)).contains(&px)
+            && (y1.min(y2)..=y1.max(y2)).contains(&py)
+        {
+            return Location::Boundary;
+        }
+
+        // Cast a ray towards +x and count the edges it crosses, treating each edge as
+        // half-open in y so that vertices on the ray are only counted once
+        if (y1 > py) != (y2 > py) && (cross > 0) == (y2 > y1) {
+            is_inside = !is_inside;
+        }
+    }
+
+    if is_inside {
+        Location::Inside
+    } else {
+        Location::Outside
+    }
+}
+
+pub fn contains(point: Point, vertices: &[Point]) -> bool {
+    locate(point, vertices) == Location::Inside
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
@@ -1,13 +1,29 @@
+pub mod bitset;
+pub mod circuit;
+pub mod dsu;
+pub mod geometry;
+pub mod hash;
+pub mod region;
+
 use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
+use std::str::FromStr;
 
+/// Reads `src/YYYY/DD/input.txt`, or the file named by the `AOC_INPUT` environment variable
+/// (e.g. to run a puzzle example instead).
 pub fn read_input(year: u32, day: u32) -> std::io::Result<String> {
-    let mut input_path = PathBuf::new();
-    input_path.push("src");
-    input_path.push(format!("{:04}", year));
-    input_path.push(format!("{:02}", day));
-    input_path.push("input.txt");
+    let input_path = match std::env::var_os("AOC_INPUT") {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let mut input_path = PathBuf::new();
+            input_path.push("src");
+            input_path.push(format!("{:04}", year));
+            input_path.push(format!("{:02}", day));
+            input_path.push("input.txt");
+            input_path
+        }
+    };
 
     let mut input_file = File::open(input_path)?;
     let mut input_string = String::new();
@@ -15,3 +31,32 @@ pub fn read_input(year: u32, day: u32) -> std::io::Result<String> {
 
     Ok(String::from(input_string.trim()))
 }
+
+/// Value of a `--name value` or `--name=value` command line option, if given. A value that
+/// doesn't parse is an `InvalidInput` error.
+pub fn option<T: FromStr>(name: &str) -> std::io::Result<Option<T>> {
+    let flag = format!("--{}", name);
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let value = if arg == flag {
+            args.next()
+        } else {
+            arg.strip_prefix(&flag)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(str::to_owned)
+        };
+
+        if let Some(value) = value {
+            return match value.parse() {
+                Ok(value) => Ok(Some(value)),
+                Err(_) => Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("invalid value `{}` for {}", value, flag),
+                )),
+            };
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,191 @@
+use crate::dsu::DisjointSet;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bounds {
+    pub min_row: usize,
+    pub min_col: usize,
+    pub max_row: usize,
+    pub max_col: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub cells: Vec<(usize, usize)>,
+    pub area: usize,
+    pub perimeter: usize,
+    pub sides: usize,
+    pub bounds: Bounds,
+    pub holes: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Regions {
+    labels: Vec<Vec<usize>>,
+    regions: Vec<Region>,
+}
+
+impl Regions {
+    /// Splits `grid` into regions of orthogonally adjacent cells for which `connected` holds.
+    /// Labels are assigned in row-major order of each region's first cell.
+    pub fn find<T>(grid: &[Vec<T>], connected: impl Fn(&T, &T) -> bool) -> Self {
+        let n = grid.len();
+        let m = grid.first().map_or(0, |row| row.len());
+        let mut set = DisjointSet::from_grid(grid, connected);
+
+        let mut root_labels = vec![None; n * m];
+        let mut labels = Vec::with_capacity(n);
+        let mut regions: Vec<Region> = Vec::new();
+
+        for row in 0..n {
+            let mut label_row = Vec::with_capacity(m);
+            for col in 0..m {
+                let root = set.find(row * m + col);
+                let label = *root_labels[root].get_or_insert_with(|| {
+                    regions.push(Region {
+                        bounds: Bounds {
+                            min_row: row,
+                            min_col: col,
+                            max_row: row,
+                            max_col: col,
+                        },
+                        ..Default::default()
+                    });
+                    regions.len() - 1
+                });
+
+                label_row.push(label);
+
+                let region = &mut regions[label];
+                region.cells.push((row, col));
+                region.area += 1;
+                region.bounds.min_col = region.bounds.min_col.min(col);
+                region.bounds.max_row = region.bounds.max_row.max(row);
+                region.bounds.max_col = region.bounds.max_col.max(col);
+            }
+            labels.push(label_row);
+        }
+
+        let mut regions = Self { labels, regions };
+        for label in 0..regions.regions.len() {
+            regions.measure(label);
+        }
+
+        regions
+    }
+
+    pub fn label(&self, row: usize, col: usize) -> usize {
+        self.labels[row][col]
+    }
+
+    pub fn get(&self, label: usize) -> &Region {
+        &self.regions[label]
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Region> {
+        self.regions.iter()
+    }
+
+    fn contains(&self, label: usize, row: isize, col: isize) -> bool {
+        (0..self.labels.len() as isize).contains(&row)
+            && (0..self.labels[0].len() as isize).contains(&col)
+            && self.labels[row as usize][col as usize] == label
+    }
+
+    fn measure(&mut self, label: usize) {
+        let mut perimeter = 0;
+        let mut sides = 0;
+
+        for &(row, col) in self.regions[label].cells.iter() {
+            let row = row as isize;
+            let col = col as isize;
+            let inside = |dr: isize, dc: isize| self.contains(label, row + dr, col + dc);
+
+            for (dr, dc) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                if !inside(dr, dc) {
+                    perimeter += 1;
+                }
+            }
+
+            // Number of sides = number of corners
+            for (dr, dc) in [(-1, -1), (-1, 1), (1, -1), (1, 1)] {
+                let vertical = inside(dr, 0);
+                let horizontal = inside(0, dc);
+
+                if !vertical && !horizontal {
+                    sides += 1; // convex corner
+                }
+
+                if vertical && horizontal && !inside(dr, dc) {
+                    sides += 1; // concave corner
+                }
+            }
+        }
+
+        let holes = self.count_holes(label);
+
+        let region = &mut self.regions[label];
+        region.perimeter = perimeter;
+        region.sides = sides;
+        region.holes = holes;
+    }
+
+    fn count_holes(&self, label: usize) -> usize {
+        let Bounds {
+            min_row,
+            min_col,
+            max_row,
+            max_col,
+        } = self.regions[label].bounds;
+
+        // Scan the bounding box with a one cell margin, so that everything outside the
+        // region is reachable from the top left corner. The background is 8-connected,
+        // since two diagonal region cells don't seal off anything between them.
+        let rows = (max_row - min_row + 3) as isize;
+        let cols = (max_col - min_col + 3) as isize;
+        let is_background = |r: isize, c: isize| {
+            !self.contains(label, r + min_row as isize - 1, c + min_col as isize - 1)
+        };
+
+        let mut scanned = vec![vec![false; cols as usize]; rows as usize];
+        let mut components = 0;
+
+        for row in 0..rows {
+            for col in 0..cols {
+                if scanned[row as usize][col as usize] || !is_background(row, col) {
+                    continue;
+                }
+
+                components += 1;
+                scanned[row as usize][col as usize] = true;
+
+                let mut to_scan = vec![(row, col)];
+                while let Some((row, col)) = to_scan.pop() {
+                    for dr in -1..=1 {
+                        for dc in -1..=1 {
+                            let (r, c) = (row + dr, col + dc);
+                            if (0..rows).contains(&r)
+                                && (0..cols).contains(&c)
+                                && !scanned[r as usize][c as usize]
+                                && is_background(r, c)
+                            {
+                                scanned[r as usize][c as usize] = true;
+                                to_scan.push((r, c));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // The first component found is the outside
+        components - 1
+    }
+}